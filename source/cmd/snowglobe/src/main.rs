@@ -1,3 +1,40 @@
+use snowfall_voxel::prelude::*;
+
+/// Seed used when none is given on the command line
+const DEFAULT_SEED: u64 = 1;
+
+/// Highest z scanned when looking for the surface, above any terrain
+/// NoiseTerrain produces
+const SKY_Z: i32 = 256;
+
+/// The world snowglobe starts in: unbounded noise terrain, generated a chunk
+/// at a time as it is explored.
+fn default_world(seed: u64) -> VoxelGrid {
+    VoxelGrid::new().with_generator(NoiseTerrain::new(seed))
+}
+
+/// Finds the top non-empty voxel of the column, generating its chunks as
+/// needed.  Returns its z and block id.
+fn surface_at(world: &mut VoxelGrid, x: i32, y: i32) -> Option<(i32, String)> {
+    let z = (-SKY_Z..=SKY_Z)
+        .rev()
+        .find(|&z| !world.is_empty((x, y, z)))?;
+    let block = world.get((x, y, z))?;
+    Some((z, block.id.clone()))
+}
+
 fn main() {
-    println!("Hello, world!");
+    let seed = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(DEFAULT_SEED);
+
+    let mut world = default_world(seed);
+    match surface_at(&mut world, 0, 0) {
+        Some((z, id)) => println!(
+            "snowglobe: world seed {}, surface at the origin is {} at z = {}",
+            seed, id, z
+        ),
+        None => println!("snowglobe: world seed {}, no surface at the origin", seed),
+    }
 }
@@ -13,4 +13,25 @@ pub enum Error {
 
     #[error("Invalid file version")]
     FileVersion(String),
+
+    #[error("Decompression failed: {0}")]
+    Decompression(String),
+
+    #[error("Corrupt data: {0}")]
+    CorruptData(String),
+
+    #[error("Invalid .vox data: {0}")]
+    VoxFormat(String),
+
+    #[error("Image error: {0}")]
+    Image(String),
+
+    #[error("Invalid schematic: {0}")]
+    Schematic(String),
+
+    #[error("Generator error: {0}")]
+    Generator(String),
+
+    #[error("Unknown block id '{0}'")]
+    UnknownBlock(String),
 }
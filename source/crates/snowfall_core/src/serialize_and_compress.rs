@@ -1,5 +1,5 @@
 use crate::internal::*;
-use flate2::{write::DeflateEncoder, Compression};
+use flate2::write::DeflateEncoder;
 use std::io::{Read, Write};
 
 pub fn serialize_to_bytes<T>(data: &T) -> Result<Vec<u8>, Error>
@@ -16,25 +16,147 @@ where
     Ok(bincode::deserialize(data)?)
 }
 
-pub fn serialize_and_compress<T>(data: &T) -> Vec<u8>
+// ----------------------------------------------------------------------------
+// Codecs
+// ----------------------------------------------------------------------------
+
+/// Compression algorithm.  The numeric id is what file formats store in
+/// their headers, so existing ids must never be reassigned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Codec {
+    None,
+    Deflate,
+    Lz4,
+}
+
+impl Codec {
+    pub fn id(&self) -> u8 {
+        match self {
+            Codec::None => 0,
+            Codec::Deflate => 1,
+            Codec::Lz4 => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Result<Codec, Error> {
+        match id {
+            0 => Ok(Codec::None),
+            1 => Ok(Codec::Deflate),
+            2 => Ok(Codec::Lz4),
+            _ => Err(Error::CorruptData(format!("unknown codec id {}", id))),
+        }
+    }
+}
+
+/// A codec plus its level.  Levels follow zlib conventions (0-9, higher is
+/// smaller and slower) and are ignored by codecs that have no levels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Compression {
+    pub codec: Codec,
+    pub level: u32,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self {
+            codec: Codec::Deflate,
+            level: 6,
+        }
+    }
+}
+
+impl Compression {
+    pub fn none() -> Self {
+        Self {
+            codec: Codec::None,
+            level: 0,
+        }
+    }
+
+    pub fn deflate(level: u32) -> Self {
+        Self {
+            codec: Codec::Deflate,
+            level: level.min(9),
+        }
+    }
+
+    /// Favors speed, e.g. for autosaves.
+    pub fn fast() -> Self {
+        Self {
+            codec: Codec::Lz4,
+            level: 0,
+        }
+    }
+
+    /// Favors size, e.g. for distribution builds.
+    pub fn best() -> Self {
+        Self::deflate(9)
+    }
+}
+
+pub fn compress(data: &[u8], compression: &Compression) -> Result<Vec<u8>, Error> {
+    match compression.codec {
+        Codec::None => Ok(data.to_vec()),
+        Codec::Deflate => {
+            let level = flate2::Compression::new(compression.level.min(9));
+            let mut encoder = DeflateEncoder::new(Vec::new(), level);
+            encoder.write_all(data)?;
+            Ok(encoder.finish()?)
+        }
+        Codec::Lz4 => Ok(lz4_flex::block::compress_prepend_size(data)),
+    }
+}
+
+pub fn decompress(data: &[u8], codec: Codec) -> Result<Vec<u8>, Error> {
+    match codec {
+        Codec::None => Ok(data.to_vec()),
+        Codec::Deflate => {
+            let mut decoder = flate2::read::DeflateDecoder::new(data);
+            let mut buffer = Vec::new();
+            decoder
+                .read_to_end(&mut buffer)
+                .map_err(|e| Error::Decompression(e.to_string()))?;
+            Ok(buffer)
+        }
+        Codec::Lz4 => lz4_flex::block::decompress_size_prepended(data)
+            .map_err(|e| Error::Decompression(e.to_string())),
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Serialization
+// ----------------------------------------------------------------------------
+
+/// Serializes with bincode and compresses with the default deflate settings.
+pub fn serialize_and_compress<T>(data: &T) -> Result<Vec<u8>, Error>
+where
+    T: serde::Serialize,
+{
+    serialize_and_compress_with(data, &Compression::default())
+}
+
+pub fn serialize_and_compress_with<T>(data: &T, compression: &Compression) -> Result<Vec<u8>, Error>
 where
     T: serde::Serialize,
 {
-    let buffer = bincode::serialize(data).expect("Serialization failed");
+    compress(&bincode::serialize(data)?, compression)
+}
 
-    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
-    encoder.write_all(&buffer).expect("Failed to compress");
-    encoder.finish().expect("Failed to finish compression")
+/// Inverse of `serialize_and_compress` for deflate compressed data.
+pub fn decompress_and_deserialize<T>(data: &[u8]) -> Result<T, Error>
+where
+    T: serde::de::DeserializeOwned,
+{
+    decompress_and_deserialize_with(data, Codec::Deflate)
 }
 
-pub fn decompress_and_deserialize<T>(data: &[u8]) -> T
+/// Errors in the compressed stream are reported as `Error::Decompression`
+/// and data that decompresses but does not decode as `Error::CorruptData`,
+/// so callers can distinguish a damaged file from a bug in the format.
+pub fn decompress_and_deserialize_with<T>(data: &[u8], codec: Codec) -> Result<T, Error>
 where
     T: serde::de::DeserializeOwned,
 {
-    let mut decoder = flate2::read::DeflateDecoder::new(data);
-    let mut buffer = Vec::new();
-    decoder
-        .read_to_end(&mut buffer)
-        .expect("Failed to decompress");
-    bincode::deserialize(&buffer).expect("Deserialization failed")
+    let buffer = decompress(data, codec)?;
+    bincode::deserialize(&buffer).map_err(|e| Error::CorruptData(e.to_string()))
 }
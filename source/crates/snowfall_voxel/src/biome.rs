@@ -0,0 +1,300 @@
+//----------------------------------------------------------------------------//
+//! Biomes chosen per column from temperature and moisture noise.
+//!
+//! A scene can carry a `BiomeMap` so that the generators filling it agree on
+//! what each area looks like: terrain generators pick the surface and soil
+//! blocks of the biome under each column, and scattering generators pick
+//! their vegetation, and how densely to place it, from the biome under each
+//! candidate position.  Generators fall back to their own choices when the
+//! scene has no biome map or the caller asked for something specific.
+//!
+//! Temperature and moisture are independent 2D noise fields in 0.0 to 1.0,
+//! and the biome is a lookup on the two, roughly following a Whittaker
+//! diagram.
+//!
+//----------------------------------------------------------------------------//
+
+use crate::internal::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Biome {
+    Tundra,
+    Taiga,
+    Grassland,
+    Forest,
+    Swamp,
+    Desert,
+    Savanna,
+}
+
+struct BiomeDef {
+    name: &'static str,
+    surface: [(&'static str, (u8, u8, u8)); 2],
+    soil: (&'static str, (u8, u8, u8)),
+    vegetation: &'static [(u32, &'static str)],
+    density: f32,
+}
+
+// Indexed by `Biome as usize`
+const BIOMES: [BiomeDef; 7] = [
+    BiomeDef {
+        name: "tundra",
+        surface: [("tundra1", (150, 150, 135)), ("tundra2", (140, 142, 130))],
+        soil: ("tundra_soil", (70, 64, 56)),
+        vegetation: &[(10, "bare_tree")],
+        density: 0.1,
+    },
+    BiomeDef {
+        name: "taiga",
+        surface: [("taiga1", (30, 70, 40)), ("taiga2", (25, 60, 35))],
+        soil: ("taiga_soil", (40, 30, 20)),
+        vegetation: &[(90, "pine_tree"), (10, "bare_tree")],
+        density: 0.8,
+    },
+    BiomeDef {
+        name: "grassland",
+        surface: [("grassland1", (70, 120, 30)), ("grassland2", (60, 110, 25))],
+        soil: ("grassland_soil", (45, 35, 20)),
+        vegetation: &[(70, "flower_cluster"), (15, "tree1"), (15, "tree2")],
+        density: 0.4,
+    },
+    BiomeDef {
+        name: "forest",
+        surface: [("forest1", (5, 60, 10)), ("forest2", (3, 45, 2))],
+        soil: ("forest_soil", (10, 8, 4)),
+        vegetation: &[(10, "tree1"), (10, "tree2"), (80, "pine_tree")],
+        density: 1.0,
+    },
+    BiomeDef {
+        name: "swamp",
+        surface: [("swamp1", (40, 55, 25)), ("swamp2", (35, 48, 22))],
+        soil: ("swamp_soil", (30, 28, 18)),
+        vegetation: &[(60, "bare_tree"), (40, "tree2")],
+        density: 0.5,
+    },
+    BiomeDef {
+        name: "desert",
+        surface: [("desert1", (210, 190, 130)), ("desert2", (200, 180, 120))],
+        soil: ("desert_soil", (180, 160, 105)),
+        vegetation: &[(10, "bare_tree")],
+        density: 0.05,
+    },
+    BiomeDef {
+        name: "savanna",
+        surface: [("savanna1", (150, 140, 60)), ("savanna2", (140, 128, 52))],
+        soil: ("savanna_soil", (90, 70, 40)),
+        vegetation: &[(70, "tree2"), (30, "flower_cluster")],
+        density: 0.25,
+    },
+];
+
+impl Biome {
+    pub const ALL: [Biome; 7] = [
+        Biome::Tundra,
+        Biome::Taiga,
+        Biome::Grassland,
+        Biome::Forest,
+        Biome::Swamp,
+        Biome::Desert,
+        Biome::Savanna,
+    ];
+
+    /// Looks up the biome for a temperature and moisture, each from 0.0 to
+    /// 1.0.
+    pub fn classify(temperature: f32, moisture: f32) -> Biome {
+        if temperature < 0.4 {
+            if moisture < 0.45 {
+                Biome::Tundra
+            } else {
+                Biome::Taiga
+            }
+        } else if temperature < 0.6 {
+            if moisture < 0.4 {
+                Biome::Grassland
+            } else if moisture < 0.6 {
+                Biome::Forest
+            } else {
+                Biome::Swamp
+            }
+        } else if moisture < 0.5 {
+            Biome::Desert
+        } else {
+            Biome::Savanna
+        }
+    }
+
+    fn def(&self) -> &'static BiomeDef {
+        &BIOMES[*self as usize]
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.def().name
+    }
+
+    /// Ids of the two shades of block for the top voxel of a column.
+    pub fn surface_ids(&self) -> [&'static str; 2] {
+        let [a, b] = &self.def().surface;
+        [a.0, b.0]
+    }
+
+    /// Id of the block for the voxels under the surface.
+    pub fn soil_id(&self) -> &'static str {
+        self.def().soil.0
+    }
+
+    /// The surface and soil blocks, for registering in a palette.
+    pub fn blocks(&self) -> Vec<Block> {
+        let def = self.def();
+        def.surface
+            .iter()
+            .chain(std::iter::once(&def.soil))
+            .map(|(id, (r, g, b))| Block::color(*id, *r, *g, *b))
+            .collect()
+    }
+
+    /// Weighted list of [weight, generator] pairs for the plants that grow
+    /// in the biome, in the form `cluster2` takes.
+    pub fn vegetation(&self) -> Vec<(u32, String)> {
+        self.def()
+            .vegetation
+            .iter()
+            .map(|(weight, name)| (*weight, name.to_string()))
+            .collect()
+    }
+
+    /// Fraction, from 0.0 to 1.0, of the decorations a scattering generator
+    /// would place in a dense forest that it should place here.
+    pub fn density(&self) -> f32 {
+        self.def().density
+    }
+}
+
+pub struct BiomeMap {
+    temperature: NoiseGen,
+    moisture: NoiseGen,
+}
+
+impl BiomeMap {
+    /// Creates a map whose regions are roughly `scale` voxels across.
+    pub fn new(rng: &mut RNG, scale: f32) -> Self {
+        Self {
+            temperature: rng.open_simplex().scale(scale).build(),
+            moisture: rng.open_simplex().scale(scale).build(),
+        }
+    }
+
+    pub fn temperature_at(&self, x: i32, y: i32) -> f32 {
+        self.temperature.gen_2d(x as f32, y as f32)
+    }
+
+    pub fn moisture_at(&self, x: i32, y: i32) -> f32 {
+        self.moisture.gen_2d(x as f32, y as f32)
+    }
+
+    pub fn biome_at(&self, x: i32, y: i32) -> Biome {
+        Biome::classify(self.temperature_at(x, y), self.moisture_at(x, y))
+    }
+}
+
+impl Scene2 {
+    /// Biome of the column, or None if the scene has no biome map.
+    pub fn biome_at(&self, x: i32, y: i32) -> Option<Biome> {
+        self.biomes.as_ref().map(|biomes| biomes.biome_at(x, y))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(Biome::classify(0.2, 0.2), Biome::Tundra);
+        assert_eq!(Biome::classify(0.2, 0.8), Biome::Taiga);
+        assert_eq!(Biome::classify(0.5, 0.2), Biome::Grassland);
+        assert_eq!(Biome::classify(0.5, 0.5), Biome::Forest);
+        assert_eq!(Biome::classify(0.5, 0.8), Biome::Swamp);
+        assert_eq!(Biome::classify(0.8, 0.2), Biome::Desert);
+        assert_eq!(Biome::classify(0.8, 0.8), Biome::Savanna);
+
+        for biome in Biome::ALL {
+            assert_eq!(biome.def().name, serde_json::to_value(biome).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_vegetation_is_registered() {
+        let registry = GeneratorRegistry::builtin();
+        for biome in Biome::ALL {
+            assert!(biome.density() > 0.0 && biome.density() <= 1.0);
+            for (_, name) in biome.vegetation() {
+                assert!(registry.get(&name).is_some(), "{}: {}", biome.name(), name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_map() {
+        let map = BiomeMap::new(&mut RNG::new(7), 64.0);
+        let again = BiomeMap::new(&mut RNG::new(7), 64.0);
+
+        let mut seen = Vec::new();
+        for y in (-512..512).step_by(16) {
+            for x in (-512..512).step_by(16) {
+                let biome = map.biome_at(x, y);
+                assert_eq!(biome, again.biome_at(x, y));
+                if !seen.contains(&biome) {
+                    seen.push(biome);
+                }
+            }
+        }
+        assert!(seen.len() >= 4, "only saw {:?}", seen);
+    }
+
+    #[test]
+    fn test_cluster_uses_biome_vegetation() {
+        let mut scene = Scene2::new();
+        scene.biomes = Some(BiomeMap::new(&mut RNG::new(3), 32.0));
+        scene.terrain = generators::flat_ground(&GenContext::new("flat_ground", 1), &mut scene);
+        for x in -64..=64 {
+            let biome = scene.biome_at(x, 0).unwrap();
+            let top = scene.terrain.top_block_at(x, 0).unwrap();
+            assert!(biome.surface_ids().contains(&top.id.as_str()));
+        }
+
+        let ctx = GenContext::new("cluster2", 5).with_params(serde_json::json!({
+            "count": [40, 40],
+            "range": 96,
+            "closest_distance": 6.0,
+        }));
+        let VoxelModel::Group(group) = generate_model(&ctx, &mut scene) else {
+            panic!("expected group");
+        };
+        assert!(!group.objects.is_empty());
+        for object in group.objects.iter() {
+            let biome = scene
+                .biome_at(object.position.x, object.position.y)
+                .unwrap();
+            assert!(
+                biome
+                    .vegetation()
+                    .iter()
+                    .any(|(_, name)| *name == object.generator_id),
+                "{} in {}",
+                object.generator_id,
+                biome.name()
+            );
+        }
+    }
+
+    #[test]
+    fn test_biome_hills_leaves_map() {
+        // Generators placed after biome_hills follow the same biomes
+        let mut scene = Scene2::new();
+        generators::biome_hills(&GenContext::new("biome_hills", 2), &mut scene).unwrap();
+        let top = scene.terrain.top_block_at(0, 0).unwrap();
+        let biome = scene.biome_at(0, 0).unwrap();
+        assert!(biome.surface_ids().contains(&top.id.as_str()));
+    }
+}
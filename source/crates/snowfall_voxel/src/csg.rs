@@ -0,0 +1,120 @@
+use crate::internal::*;
+
+/// Constructive solid geometry
+///
+/// Each operation combines this set with `other` translated by `offset` and
+/// returns a new set.  The palette of `other` is merged into the result, so
+/// the two sets do not need to share block definitions.
+impl VoxelSet {
+    /// Voxels in either set.  Where both sets have a voxel, `other` wins.
+    pub fn union(&self, other: &VoxelSet, offset: IVec3) -> VoxelSet {
+        let mut result = self.clone();
+        result.union_with(other, offset);
+        result
+    }
+
+    /// Voxels of this set that are not covered by `other`.
+    pub fn subtract(&self, other: &VoxelSet, offset: IVec3) -> VoxelSet {
+        let mut result = self.clone();
+        result.subtract_with(other, offset);
+        result
+    }
+
+    /// Voxels of this set that are also covered by `other`.  The blocks of
+    /// this set are kept, so `other` acts as a mask.
+    pub fn intersect(&self, other: &VoxelSet, offset: IVec3) -> VoxelSet {
+        let mut result = self.empty_copy();
+        for (p, index) in self.index_iter() {
+            if !other.is_empty(p - offset) {
+                result.set(p, index);
+            }
+        }
+        result
+    }
+
+    /// Voxels that are in exactly one of the two sets.
+    pub fn xor(&self, other: &VoxelSet, offset: IVec3) -> VoxelSet {
+        let mut result = self.empty_copy();
+        let remap = result.palette.merge(&other.palette);
+        for (p, index) in self.index_iter() {
+            if other.is_empty(p - offset) {
+                result.set(p, index);
+            }
+        }
+        for (p, index) in other.index_iter() {
+            let q = p + offset;
+            if self.is_empty(q) {
+                result.set(q, remap.get(index));
+            }
+        }
+        result
+    }
+
+    // ------------------------------------------------------------------------
+    // In-place variants
+    // ------------------------------------------------------------------------
+
+    pub fn union_with(&mut self, other: &VoxelSet, offset: IVec3) {
+        let remap = self.palette.merge(&other.palette);
+        for (p, index) in other.index_iter() {
+            self.set(p + offset, remap.get(index));
+        }
+    }
+
+    pub fn subtract_with(&mut self, other: &VoxelSet, offset: IVec3) {
+        for (p, _) in other.index_iter() {
+            let q = p + offset;
+            if !self.is_empty(q) {
+                self.clear_voxel(q);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cube(id: &str, r: u8, size: i32) -> VoxelSet {
+        let mut model = VoxelSet::new();
+        model.register_block(Block::color(id, r, 0, 0));
+        for z in 0..size {
+            for y in 0..size {
+                for x in 0..size {
+                    model.set((x, y, z), id);
+                }
+            }
+        }
+        model
+    }
+
+    #[test]
+    fn test_csg_counts() {
+        let a = cube("a", 255, 2);
+        let b = cube("b", 128, 2);
+        let offset = IVec3::new(1, 0, 0);
+
+        assert_eq!(a.union(&b, offset).voxel_iter(false).len(), 12);
+        assert_eq!(a.subtract(&b, offset).voxel_iter(false).len(), 4);
+        assert_eq!(a.intersect(&b, offset).voxel_iter(false).len(), 4);
+        assert_eq!(a.xor(&b, offset).voxel_iter(false).len(), 8);
+
+        let u = a.union(&b, offset);
+        assert_eq!(u.get_voxel((0, 0, 0)).id, "a");
+        assert_eq!(u.get_voxel((1, 0, 0)).id, "b");
+        assert_eq!(u.get_voxel((2, 1, 1)).id, "b");
+    }
+
+    #[test]
+    fn test_union_merges_conflicting_block_ids() {
+        let a = cube("stone", 255, 1);
+        let b = cube("stone", 10, 1);
+        let u = a.union(&b, IVec3::new(0, 0, 1));
+
+        let bottom = u.get_voxel((0, 0, 0));
+        let top = u.get_voxel((0, 0, 1));
+        assert_eq!(bottom.id, "stone");
+        assert_ne!(top.id, "stone");
+        assert_eq!(top.shader, BlockShader::RGB(BlockRGB { r: 10, g: 0, b: 0 }));
+    }
+}
@@ -0,0 +1,207 @@
+//----------------------------------------------------------------------------//
+//! On-disk cache of generated VoxelSets.
+//!
+//! Entries are content-addressed: the file name is a stable hash of the
+//! generator name, generator version, seed, and params, so there is nothing
+//! to keep in sync besides the files themselves.  Bumping a generator's
+//! version orphans its old entries; `invalidate`, `invalidate_generator`,
+//! and `clear` remove entries explicitly.
+//!
+//! Only generators with a version are cached (see `Generator::version`).
+//!
+//----------------------------------------------------------------------------//
+
+use crate::internal::*;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct GenCache {
+    root: PathBuf,
+}
+
+impl GenCache {
+    pub fn new<P>(root: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the cache key for the context, or None if the generator's
+    /// output is not cacheable.
+    pub fn key(ctx: &GenContext) -> Option<String> {
+        let version = ctx.registry.get(&ctx.generator)?.version()?;
+
+        let mut text = String::new();
+        text.push_str(&ctx.generator);
+        text.push('\n');
+        text.push_str(&version.to_string());
+        text.push('\n');
+        text.push_str(&ctx.seed.to_string());
+        text.push('\n');
+        write_canonical_json(&ctx.params, &mut text);
+        Some(format!("{:016x}", fnv1a_64(text.as_bytes())))
+    }
+
+    fn entry_path(&self, ctx: &GenContext) -> Option<PathBuf> {
+        let key = Self::key(ctx)?;
+        Some(self.root.join(&ctx.generator).join(format!("{}.vset", key)))
+    }
+
+    /// Returns the cached output for the context if there is one.  Unreadable
+    /// entries are treated as misses so they get regenerated.
+    pub fn get(&self, ctx: &GenContext) -> Option<VoxelSet> {
+        let path = self.entry_path(ctx)?;
+        let bytes = std::fs::read(&path).ok()?;
+        match VoxelSet::deserialize_from_bytes(&bytes) {
+            Ok(model) => Some(model),
+            Err(e) => {
+                log::warn!("Ignoring cache entry {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Stores the output for the context.  Does nothing if the generator is
+    /// not cacheable.
+    pub fn put(&self, ctx: &GenContext, model: &VoxelSet) -> Result<(), Error> {
+        let Some(path) = self.entry_path(ctx) else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(path.parent().unwrap())?;
+
+        // Write then rename so a concurrent reader never sees a partial file
+        let temp = path.with_extension("vset.tmp");
+        std::fs::write(&temp, model.serialize_to_bytes_with(&Compression::fast())?)?;
+        std::fs::rename(&temp, &path)?;
+        Ok(())
+    }
+
+    /// Removes the cached output for the context.
+    pub fn invalidate(&self, ctx: &GenContext) -> Result<(), Error> {
+        if let Some(path) = self.entry_path(ctx) {
+            remove_if_exists(&path)?;
+        }
+        Ok(())
+    }
+
+    /// Removes all cached outputs of a generator.
+    pub fn invalidate_generator(&self, generator: &str) -> Result<(), Error> {
+        remove_if_exists(&self.root.join(generator))
+    }
+
+    /// Removes every cached output.
+    pub fn clear(&self) -> Result<(), Error> {
+        remove_if_exists(&self.root)
+    }
+}
+
+fn remove_if_exists(path: &Path) -> Result<(), Error> {
+    let result = if path.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    };
+    match result {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Writes JSON with object keys sorted so that the key does not depend on
+/// the order params were written in.
+fn write_canonical_json(value: &serde_json::Value, out: &mut String) {
+    match value {
+        serde_json::Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical_json(item, out);
+            }
+            out.push(']');
+        }
+        serde_json::Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical_json(&map[key], out);
+            }
+            out.push('}');
+        }
+        _ => out.push_str(&value.to_string()),
+    }
+}
+
+/// FNV-1a is used rather than std's hasher since the latter is not
+/// guaranteed to be stable across Rust releases.
+pub(crate) fn fnv1a_64(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cache_key() {
+        let a = GenContext::new("tree1", 7).with_params(serde_json::json!({"a": 1, "b": [2]}));
+        let b = GenContext::new("tree1", 7).with_params(serde_json::json!({"b": [2], "a": 1}));
+        assert_eq!(GenCache::key(&a), GenCache::key(&b));
+        assert_eq!(
+            GenCache::key(&a.with_center(IVec3::new(5, 5, 0))),
+            GenCache::key(&a)
+        );
+        assert_ne!(GenCache::key(&a), GenCache::key(&a.fork("tree1", 8)));
+        assert_ne!(
+            GenCache::key(&a),
+            GenCache::key(&a.with_params(serde_json::Value::Null))
+        );
+
+        // Scene-dependent generators are never cached
+        assert_eq!(GenCache::key(&GenContext::new("small_hill", 7)), None);
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let root = std::env::temp_dir().join(format!("snowfall_gen_cache_{}", std::process::id()));
+        let cache = GenCache::new(&root);
+        let ctx = GenContext::new("tree2", 42).with_cache(cache.clone());
+
+        let mut scene = Scene2::new();
+        let VoxelModel::VoxelSet(generated) = generate_model(&ctx, &mut scene) else {
+            panic!("expected a VoxelSet");
+        };
+        let cached = cache.get(&ctx).unwrap();
+        assert_eq!(
+            cached.voxel_iter(false).len(),
+            generated.voxel_iter(false).len()
+        );
+
+        cache.invalidate(&ctx).unwrap();
+        assert!(cache.get(&ctx).is_none());
+
+        generate_model(&ctx, &mut scene);
+        cache.invalidate_generator("tree2").unwrap();
+        assert!(cache.get(&ctx).is_none());
+
+        cache.clear().unwrap();
+        assert!(!root.exists());
+    }
+}
@@ -0,0 +1,393 @@
+//----------------------------------------------------------------------------//
+//! Generator trait and the registry `generate_model` looks generators up in.
+//!
+//! The built-in generators are registered in `generators::register_builtins`.
+//! Other crates add their own by extending a registry and attaching it to the
+//! context:
+//!
+//! ```ignore
+//! let registry = GeneratorRegistry::with_builtins().register(
+//!     FnGenerator::<NoParams>::new("my_rock", "A single rock", |ctx, scene| {
+//!         Ok(my_rock(ctx, scene).into())
+//!     }),
+//! );
+//! let ctx = GenContext::new("my_rock", 42).with_registry(Arc::new(registry));
+//! ```
+//!
+//----------------------------------------------------------------------------//
+
+use crate::internal::*;
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::sync::{Arc, OnceLock};
+
+pub trait Generator: Send + Sync {
+    fn name(&self) -> &str;
+    fn description(&self) -> &str;
+
+    /// Version of the generator's output.  Generators with a version must
+    /// produce output that depends only on the seed and params, which lets
+    /// the output be cached; bump the version whenever the output changes.
+    /// Generators that read the scene or asset files return None.
+    fn version(&self) -> Option<u32>;
+
+    /// Rust type name of the generator's params, for diagnostics.
+    fn params_type(&self) -> &'static str;
+
+    /// The params used when none are given.
+    fn default_params(&self) -> serde_json::Value;
+
+    fn params_schema(&self) -> ParamSchema;
+
+    /// Generators return an error rather than an empty model when they
+    /// cannot run, e.g. when an asset file fails to load.
+    fn generate(&self, ctx: &GenContext, scene: &mut Scene2) -> Result<VoxelModel, Error>;
+
+    /// Checks the params against the schema.  `null` means "no params" and
+    /// is always valid.
+    fn validate_params(&self, params: &serde_json::Value) -> Result<(), Error> {
+        if params.is_null() {
+            return Ok(());
+        }
+        self.params_schema()
+            .validate(params, "params")
+            .map_err(|e| Error::Generator(format!("{}: {}", self.name(), e)))
+    }
+
+    fn params_json_schema(&self) -> serde_json::Value {
+        let mut schema = self.params_schema().to_json_schema();
+        schema["title"] = serde_json::json!(self.name());
+        schema["description"] = serde_json::json!(self.description());
+        schema
+    }
+}
+
+pub type GeneratorFn = fn(&GenContext, &mut Scene2) -> Result<VoxelModel, Error>;
+
+/// Params type for generators that take no params.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct NoParams {}
+
+impl GeneratorParams for NoParams {
+    fn schema() -> ParamSchema {
+        ParamSchema::Object(vec![])
+    }
+}
+
+/// Adapts a plain function to the Generator trait.  `P` is the params type
+/// the function reads via `ctx.params()`.
+pub struct FnGenerator<P> {
+    name: String,
+    description: String,
+    version: Option<u32>,
+    func: GeneratorFn,
+    params: PhantomData<fn() -> P>,
+}
+
+impl<P> FnGenerator<P> {
+    pub fn new(name: &str, description: &str, func: GeneratorFn) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            version: None,
+            func,
+            params: PhantomData,
+        }
+    }
+
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = Some(version);
+        self
+    }
+}
+
+impl<P> Generator for FnGenerator<P>
+where
+    P: GeneratorParams + 'static,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn version(&self) -> Option<u32> {
+        self.version
+    }
+
+    fn params_type(&self) -> &'static str {
+        std::any::type_name::<P>()
+    }
+
+    fn default_params(&self) -> serde_json::Value {
+        serde_json::to_value(P::default()).unwrap()
+    }
+
+    fn params_schema(&self) -> ParamSchema {
+        P::schema()
+    }
+
+    fn generate(&self, ctx: &GenContext, scene: &mut Scene2) -> Result<VoxelModel, Error> {
+        (self.func)(ctx, scene)
+    }
+
+    fn validate_params(&self, params: &serde_json::Value) -> Result<(), Error> {
+        if params.is_null() {
+            return Ok(());
+        }
+        P::schema()
+            .validate(params, "params")
+            .and_then(|_| {
+                // Catch anything the schema doesn't describe precisely, such
+                // as an integer that overflows the field's type
+                serde_json::from_value::<P>(params.clone())
+                    .map_err(|e| format!("params: {}", e))
+                    .and_then(|p| p.validate().map_err(|e| format!("params.{}", e)))
+            })
+            .map_err(|e| Error::Generator(format!("{}: {}", self.name(), e)))
+    }
+}
+
+/// Prefixes a generator error's message with where the error occurred.
+pub(crate) fn error_context(e: Error, context: &str) -> Error {
+    match e {
+        Error::Generator(msg) => Error::Generator(format!("{}: {}", context, msg)),
+        e => e,
+    }
+}
+
+#[derive(Default)]
+pub struct GeneratorRegistry {
+    generators: BTreeMap<String, Box<dyn Generator>>,
+}
+
+impl GeneratorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A new registry containing the built-in generators, for extending.
+    pub fn with_builtins() -> Self {
+        generators::register_builtins(Self::new())
+    }
+
+    /// The shared registry of built-in generators that contexts use by
+    /// default.
+    pub fn builtin() -> Arc<Self> {
+        static BUILTIN: OnceLock<Arc<GeneratorRegistry>> = OnceLock::new();
+        BUILTIN
+            .get_or_init(|| Arc::new(Self::with_builtins()))
+            .clone()
+    }
+
+    /// Adds the generator, replacing any existing generator of the same name.
+    pub fn register<G>(mut self, generator: G) -> Self
+    where
+        G: Generator + 'static,
+    {
+        self.generators
+            .insert(generator.name().to_string(), Box::new(generator));
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Generator> {
+        self.generators.get(name).map(|g| g.as_ref())
+    }
+
+    /// Iterates the generators in name order.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Generator> {
+        self.generators.values().map(|g| g.as_ref())
+    }
+
+    /// Checks that the named generator exists and accepts the params.
+    pub fn validate(&self, generator: &str, params: &serde_json::Value) -> Result<(), Error> {
+        let Some(generator) = self.get(generator) else {
+            return Err(Error::Generator(format!(
+                "unknown generator '{}'",
+                generator
+            )));
+        };
+        generator.validate_params(params)
+    }
+
+    /// Validates every model reference in the scene.
+    pub fn validate_scene(&self, scene: &VoxelScene) -> Result<(), Error> {
+        for (i, layer) in scene.layers.iter().enumerate() {
+            for (j, model_ref) in layer.models.iter().enumerate() {
+                self.validate(&model_ref.model_id, &model_ref.params)
+                    .map_err(|e| error_context(e, &format!("layers[{}].models[{}]", i, j)))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// JSON Schema for a `VoxelModelRef`, selecting the params schema based
+    /// on `model_id`.  Editors can use this to check and complete the models
+    /// in scene files.
+    pub fn model_ref_json_schema(&self) -> serde_json::Value {
+        use serde_json::json;
+
+        let names: Vec<&str> = self.iter().map(|g| g.name()).collect();
+        let cases: Vec<serde_json::Value> = self
+            .iter()
+            .map(|g| {
+                json!({
+                    "if": { "properties": { "model_id": { "const": g.name() } } },
+                    "then": {
+                        "properties": {
+                            "params": {
+                                "anyOf": [g.params_json_schema(), { "type": "null" }]
+                            }
+                        }
+                    },
+                })
+            })
+            .collect();
+
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "VoxelModelRef",
+            "type": "object",
+            "properties": {
+                "model_id": { "enum": names },
+                "seed": { "type": "integer", "minimum": 0 },
+                "position": {
+                    "type": "array",
+                    "items": { "type": "integer" },
+                    "minItems": 3,
+                    "maxItems": 3,
+                },
+                "orientation": { "type": "object" },
+                "params": {},
+            },
+            "required": ["model_id", "seed", "position"],
+            "allOf": cases,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn single_voxel(_ctx: &GenContext, _scene: &mut Scene2) -> Result<VoxelModel, Error> {
+        let mut model = VoxelSet::new();
+        model.register_block(Block::color("stone", 80, 80, 80));
+        model.set_voxel((0, 0, 0), "stone");
+        Ok(model.into())
+    }
+
+    #[test]
+    fn test_register_custom_generator() {
+        let registry = GeneratorRegistry::with_builtins().register(
+            FnGenerator::<NoParams>::new("single_voxel", "One stone voxel", single_voxel)
+                .with_version(1),
+        );
+        assert!(registry.get("tree1").is_some());
+        assert_eq!(registry.get("single_voxel").unwrap().version(), Some(1));
+
+        let ctx = GenContext::new("single_voxel", 1).with_registry(Arc::new(registry));
+        let mut scene = Scene2::new();
+        let VoxelModel::VoxelSet(model) = generate_model(&ctx, &mut scene) else {
+            panic!("expected a VoxelSet");
+        };
+        assert_eq!(model.get_voxel((0, 0, 0)).id, "stone");
+
+        // The default registry is unaffected
+        let ctx = GenContext::new("single_voxel", 1);
+        assert!(matches!(
+            try_generate_model(&ctx, &mut scene),
+            Err(Error::Generator(_))
+        ));
+    }
+
+    #[test]
+    fn test_params_validated() {
+        let ctx = GenContext::new("cluster2", 1)
+            .with_params(serde_json::json!({ "range": 8, "closest_distanse": 4.0 }));
+        let Err(Error::Generator(msg)) = try_generate_model(&ctx, &mut Scene2::new()) else {
+            panic!("expected a generator error");
+        };
+        assert!(msg.starts_with("cluster2: params.closest_distanse: unknown field"));
+
+        let registry = GeneratorRegistry::builtin();
+        let err = registry
+            .validate("key", &serde_json::json!({ "color": [255, 0, 300] }))
+            .unwrap_err();
+        assert!(err.to_string().contains("params.color[2]"));
+
+        let err = registry
+            .validate("village", &serde_json::json!({ "houses": [8, 5] }))
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("params.houses: minimum 8 is greater than maximum 5"));
+
+        for (params, expected) in [
+            (
+                serde_json::json!({ "count": [9, 3] }),
+                "params.count: minimum 9 is greater than maximum 3",
+            ),
+            (serde_json::json!({ "range": -4 }), "params.range"),
+            (
+                serde_json::json!({ "generators": [] }),
+                "params.generators: must not be empty",
+            ),
+            (
+                serde_json::json!({ "generators": [[0, "tree1"], [0, "tree2"]] }),
+                "params.generators: needs at least one positive weight",
+            ),
+        ] {
+            let err = registry.validate("cluster2", &params).unwrap_err();
+            assert!(err.to_string().contains(expected), "{}", err);
+        }
+
+        // Every built-in generator accepts its own defaults
+        for generator in registry.iter() {
+            generator
+                .validate_params(&generator.default_params())
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_nested_generator_errors() {
+        // Composite generators report failures of the generators they run
+        // rather than panicking
+        let ctx = GenContext::new("cluster2", 1)
+            .with_params(serde_json::json!({ "generators": [[10, "treee"]] }));
+        let Err(Error::Generator(msg)) = try_generate_model(&ctx, &mut Scene2::new()) else {
+            panic!("expected a generator error");
+        };
+        assert!(msg.contains("unknown generator 'treee'"));
+    }
+
+    #[test]
+    fn test_validate_scene() {
+        let mut scene = VoxelScene::new();
+        scene.add_object(
+            0,
+            VoxelModelRef {
+                model_id: "flat_ground".to_string(),
+                seed: 1,
+                position: IVec3::ZERO,
+                params: serde_json::json!({ "ground_type": 3 }),
+                orientation: Orientation::default(),
+            },
+        );
+        let err = GeneratorRegistry::builtin()
+            .validate_scene(&scene)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("layers[0].models[0]: flat_ground: params.ground_type: expected a string"));
+
+        let schema = GeneratorRegistry::builtin().model_ref_json_schema();
+        assert!(schema["properties"]["model_id"]["enum"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!("cluster2")));
+    }
+}
@@ -0,0 +1,35 @@
+use crate::internal::*;
+
+/// Large hill terrain whose ground and vegetation follow a biome map.  The
+/// map is left on the scene so later generators see the same biomes, unless
+/// the scene already had a map, which is restored afterwards.
+pub fn biome_hills(ctx: &GenContext, scene: &mut Scene2) -> Result<Group, Error> {
+    let mut rng = ctx.make_rng();
+
+    let previous = scene.biomes.replace(BiomeMap::new(&mut rng, 128.0));
+    let result = biome_hills_with_map(ctx, &mut rng, scene);
+    if previous.is_some() {
+        scene.biomes = previous;
+    }
+    result
+}
+
+fn biome_hills_with_map(
+    ctx: &GenContext,
+    rng: &mut RNG,
+    scene: &mut Scene2,
+) -> Result<Group, Error> {
+    scene.terrain = generators::hill4(&ctx.fork("hill4", rng.seed8()), scene);
+
+    let mut group = Group::new();
+    for _ in 0..4 {
+        let mut ctx = ctx.fork("cluster2", rng.seed8());
+        ctx.params = serde_json::json!({
+            "count": [120, 400],
+            "range": 248,
+        });
+        let model = try_generate_model(&ctx, scene)?;
+        group.merge(model);
+    }
+    Ok(group)
+}
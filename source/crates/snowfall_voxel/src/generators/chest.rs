@@ -1,7 +1,7 @@
 use crate::internal::*;
 use bevy_color::{Hsla, Srgba};
 
-pub fn chest_cluster(ctx: &GenContext, scene: &mut Scene2) -> Group {
+pub fn chest_cluster(ctx: &GenContext, scene: &mut Scene2) -> Result<Group, Error> {
     let mut rng = ctx.make_rng();
 
     scene.terrain = generators::flat_ground(&ctx.fork("flat_ground", rng.seed8()), scene);
@@ -16,8 +16,9 @@ pub fn chest_cluster(ctx: &GenContext, scene: &mut Scene2) -> Group {
         "generators": [
             (10, "chest".to_string()), //
         ],
+        "ground": "flatten",
     });
-    let g = generate_model(&ctx, scene);
+    let g = try_generate_model(&ctx, scene)?;
     group.merge(g);
 
     let count = 64;
@@ -36,7 +37,7 @@ pub fn chest_cluster(ctx: &GenContext, scene: &mut Scene2) -> Group {
         group.push(&ctx, voxel_set);
     }
 
-    group
+    Ok(group)
 }
 
 pub fn chest(ctx: &GenContext, scene: &mut Scene2) -> VoxelSet {
@@ -66,7 +67,7 @@ pub fn chest(ctx: &GenContext, scene: &mut Scene2) -> VoxelSet {
         let b = (b as f32 * scale).round() as u8;
         Block::color("trim", r, g, b)
     });
-    voxel_set.register_block({
+    let handle_block = {
         let (r, g, b) = *rng.select(&vec![
             (5, 5, 4), //
             (123, 123, 30),
@@ -74,47 +75,32 @@ pub fn chest(ctx: &GenContext, scene: &mut Scene2) -> VoxelSet {
             (70, 66, 30),
         ]);
         Block::color("handle", r, g, b)
-    });
-
-    for dx in -width..=width as i32 {
-        for dy in -depth..=depth as i32 {
-            for dz in 0..=6 as i32 {
-                if dz == 6 {
-                    continue;
-                }
-                let k = (2 * depth) - dz;
-                if dy.abs() > k {
-                    continue;
-                }
-
-                let mut block = *rng.select(&vec!["wood1", "wood2"]);
-                if dx.abs() == width && (dy.abs() == depth || dy.abs() == k || dz == 5) {
-                    block = "trim";
-                }
-                if dz == 0 || dz == 3 {
-                    block = "trim";
-                }
-
-                voxel_set.set_voxel((dx, dy, dz), block);
-            }
-        }
-    }
-
-    let mut rect = |cx: i32, cy: i32, cz: i32, rx: i32, ry: i32, rz: i32, block: &str| {
-        let (rx, ry, rz) = (rx - 1, ry - 1, rz - 1);
-        for dx in -rx..=rx {
-            for dy in -ry..=ry {
-                for dz in -rz..=rz {
-                    voxel_set.set_voxel((cx + dx, cy + dy, cz + dz), block);
-                }
-            }
-        }
     };
 
-    if depth > 2 {
-        rect(0, -depth, 3, 2, 1, 1, "handle");
+    let mut wood = BlockSelector::uniform(&mut rng, &["wood1", "wood2"]);
+    let mut trim = BlockSelector::block("trim");
+
+    {
+        // The sides slope in toward the top of the lid
+        let mut painter = Painter::new(&mut voxel_set);
+        painter.set_mask(move |p| p.y.abs() <= 2 * depth - p.z);
+        painter.cuboid((-width, -depth, 0), (width, depth, 5), &mut wood);
+        painter.cuboid((-width, -depth, 0), (width, depth, 0), &mut trim);
+        painter.cuboid((-width, -depth, 3), (width, depth, 3), &mut trim);
+
+        // Outline the ends
+        painter.set_mask(move |p| {
+            let k = 2 * depth - p.z;
+            p.y.abs() <= k && (p.y.abs() == depth || p.y.abs() == k || p.z == 5)
+        });
+        painter.cuboid((-width, -depth, 0), (-width, depth, 5), &mut trim);
+        painter.cuboid((width, -depth, 0), (width, depth, 5), &mut trim);
     }
-    rect(0, -depth, 2, 2, 1, 1, "handle");
+
+    let mut handle = VoxelSet::new();
+    handle.register_block(handle_block);
+    handle.fill_box((-1, 0, 0), (1, 0, if depth > 2 { 1 } else { 0 }), "handle");
+    voxel_set.union_with(&handle, IVec3::new(0, -depth, 2));
 
     voxel_set
 }
@@ -2,7 +2,7 @@ use bevy_color::{Hsla, Srgba};
 
 use crate::internal::*;
 
-pub fn chest_and_key(ctx: &GenContext, scene: &mut Scene2) -> Group {
+pub fn chest_and_key(ctx: &GenContext, scene: &mut Scene2) -> Result<Group, Error> {
     let mut rng = ctx.make_rng();
 
     let mut model = Group::new();
@@ -50,9 +50,9 @@ pub fn chest_and_key(ctx: &GenContext, scene: &mut Scene2) -> Group {
             let ctx = ctx.with_params(serde_json::json!({
                 "color": rgb,
             }));
-            key(&ctx, scene)
+            key(&ctx, scene)?
         } else {
-            generators::kestrel(&ctx, scene)
+            generators::kestrel(&ctx, scene)?
         };
         let obj = ctx.to_object(voxels);
         model.objects.push(obj);
@@ -70,7 +70,7 @@ pub fn chest_and_key(ctx: &GenContext, scene: &mut Scene2) -> Group {
                     (10, "bare_tree".to_string()), //
                 ],
             });
-            let g: VoxelModel = generate_model(&ctx, scene);
+            let g: VoxelModel = try_generate_model(&ctx, scene)?;
             model.merge(g);
         }
     }
@@ -83,11 +83,12 @@ pub fn chest_and_key(ctx: &GenContext, scene: &mut Scene2) -> Group {
         "generators": [
             (10, "chest".to_string()), //
         ],
+        "ground": "flatten",
     });
-    let g = generate_model(&ctx, scene);
+    let g = try_generate_model(&ctx, scene)?;
     model.merge(g);
 
-    model
+    Ok(model)
 }
 
 pub fn hsla_to_rgb(c: &Hsla) -> (u8, u8, u8) {
@@ -100,57 +101,33 @@ pub fn hsla_to_rgb(c: &Hsla) -> (u8, u8, u8) {
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
-struct Params {
+pub(crate) struct Params {
     color: Option<(u8, u8, u8)>,
 }
 
-pub fn key(ctx: &GenContext, scene: &mut Scene2) -> VoxelSet {
+impl GeneratorParams for Params {
+    fn schema() -> ParamSchema {
+        ParamSchema::Object(vec![ParamField::optional(
+            "color",
+            "Color that replaces the sprite colors",
+            ParamSchema::rgb(),
+        )])
+    }
+}
+
+pub fn key(ctx: &GenContext, scene: &mut Scene2) -> Result<VoxelSet, Error> {
     let mut rng = ctx.make_rng();
     let params: Params = ctx.params();
-    let base_color = params.color;
-
-    let mut model = VoxelSet::new();
-    let mut block_cache: HashMap<(u8, u8, u8), PaletteIndex> = HashMap::new();
-
-    let path = "./assets/key-sprite.png";
-    let img = image::open(path)
-        .expect("Failed to open image")
-        .into_rgba8();
-
-    for y in 0..img.height() {
-        for x in 0..img.width() {
-            let pixel = img.get_pixel(x, y);
-            let data = pixel.0;
-            let r = data[0];
-            let g = data[1];
-            let b = data[2];
-            let a = data[3];
-            if a == 0 {
-                continue;
-            }
-
-            let rgb = match base_color {
-                Some(rgb) => rgb,
-                None => (r, g, b),
-            };
-            let block = *block_cache.entry(rgb).or_insert_with(|| {
-                let (r, g, b) = (rgb.0, rgb.1, rgb.2);
-                let name = format!("color_{}_{}_{}", r, g, b);
-                let block: PaletteIndex =
-                    model.register_block(Block::color(name.as_str(), r, g, b));
-                block
-            });
-
-            let vx = (x - img.width() / 2 - 1) as i32;
-            let vz = (img.height() - y - 1) as i32;
-            model.set((vx, 0, vz), block);
-        }
-    }
+    let options = ctx
+        .sprite_options()
+        .with_tint(params.color.map(SpriteTint::Replace));
+    let mut model = VoxelSet::read_sprite_file("key-sprite.png", &options)
+        .map_err(|e| Error::Generator(format!("key: {}", e)))?;
 
     model.attributes.push(VoxelSetAttribute::Scale(0.20));
 
     let zr = rng.sign() as f32 * rng.range(0.025..=0.10);
     model.attributes.push(VoxelSetAttribute::RotateZ(zr));
     model.attributes.push(VoxelSetAttribute::Unlit);
-    model
+    Ok(model)
 }
@@ -33,47 +33,26 @@ pub fn cloud(ctx: &GenContext, scene: &mut Scene2) -> VoxelSet {
 
     for _ in 0..count {
         let noise = rng.open_simplex().scale(0.25).build();
-        let noise2 = rng.open_simplex().scale(0.15).build();
+        let noise2 = rng.open_simplex().scale(0.15 * R as f32).build();
 
         let angle = rng.range(-PI / 5.0..=PI / 5.0);
         let range_x = ((R as f32) * rng.range(1.0..2.0)).round() as i32;
         let range_y = ((R as f32) * rng.range(1.0..4.0)).round() as i32;
         let range_z = ((R as f32) * rng.range(0.25..1.25)).round() as i32;
 
-        let offset_x = rng.range(-R2..=R2);
-        let offset_y = rng.range(-R2..=R2);
-        let offset_z = rng.range(-R2 / 2..=R2 / 2);
-
-        for dz in -range_x..=range_x {
-            for dy in -range_y..=range_y {
-                for dx in -range_z..=range_z {
-                    let u = dx as f32 / range_x as f32;
-                    let v = dy as f32 / range_y as f32;
-                    let w = dz as f32 / range_z as f32;
-
-                    let d2 = u * u + v * v + w * w;
-                    let r = 0.25 + 0.75 * noise.gen_3d(u, v, w);
-                    if d2 > r * r {
-                        continue;
-                    }
-
-                    let n = noise2.gen_3d(u, v, w);
-                    let block = if n < 0.35 {
-                        "cloud1"
-                    } else if n < 0.62 {
-                        "cloud2"
-                    } else {
-                        "empty"
-                    };
-
-                    let (rx, ry) = rotate_2d(dx as f32, dy as f32, angle);
-                    let dx = rx.round() as i32;
-                    let dy = ry.round() as i32;
-
-                    voxel_set.set_voxel((dx + offset_x, dy + offset_y, dz + offset_z), block);
-                }
-            }
-        }
+        let offset = IVec3::new(
+            rng.range(-R2..=R2),
+            rng.range(-R2..=R2),
+            rng.range(-R2 / 2..=R2 / 2),
+        );
+
+        // Holes where the shading noise is high break up the cloud
+        let mut shade = BlockSelector::noise(
+            noise2,
+            &[(0.35, "cloud1"), (0.62, "cloud2"), (1.0, "empty")],
+        );
+        let radii = Vec3::new(range_x as f32, range_y as f32, range_z as f32);
+        Painter::new(&mut voxel_set).blob(offset, radii, angle, &noise, 0.75, &mut shade);
     }
 
     voxel_set
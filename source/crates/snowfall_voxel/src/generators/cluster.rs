@@ -1,13 +1,30 @@
 use crate::internal::*;
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
-struct ClusterParams {
+pub(crate) struct ClusterParams {
     count: Option<[i64; 2]>,
     range: Option<i32>,
 }
 
-pub fn cluster(seed: u64, ctx: &GenContext) -> VoxelScene {
-    let mut rng = RNG::new(seed);
+impl GeneratorParams for ClusterParams {
+    fn schema() -> ParamSchema {
+        ParamSchema::Object(vec![
+            ParamField::optional(
+                "count",
+                "Minimum and maximum number of trees",
+                ParamSchema::Tuple(vec![ParamSchema::integer(); 2]),
+            ),
+            ParamField::optional(
+                "range",
+                "Maximum X and Y distance from the center",
+                ParamSchema::integer(),
+            ),
+        ])
+    }
+}
+
+pub fn cluster(ctx: &GenContext, _scene: &mut Scene2) -> VoxelScene {
+    let mut rng = ctx.make_rng();
 
     let mut params: ClusterParams = ctx.params();
     let count_range = params.count.get_or_insert([12, 24]);
@@ -43,6 +60,7 @@ pub fn cluster(seed: u64, ctx: &GenContext) -> VoxelScene {
                 seed,
                 position,
                 params: serde_json::Value::Null,
+                orientation: Orientation::default(),
             },
         );
         count -= 1;
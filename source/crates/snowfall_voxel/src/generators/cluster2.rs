@@ -1,18 +1,82 @@
 use crate::{internal::*, voxel_set};
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
-struct ClusterParams {
+pub(crate) struct ClusterParams {
     count: Option<[i64; 2]>,
     range: Option<i32>,
     closest_distance: Option<f32>,
     generators: Option<Vec<(u32, String)>>,
     drop_to_ground: Option<bool>,
+    ground: Option<GroundMode>,
 }
 
-pub fn cluster2(ctx: &GenContext, scene: &mut Scene2) -> Group {
+impl GeneratorParams for ClusterParams {
+    fn schema() -> ParamSchema {
+        ParamSchema::Object(vec![
+            ParamField::optional(
+                "count",
+                "Minimum and maximum number of models",
+                ParamSchema::Tuple(vec![ParamSchema::integer_range(0, 1024); 2]),
+            ),
+            ParamField::optional(
+                "range",
+                "Maximum X and Y distance from the center",
+                ParamSchema::integer_range(0, 1024),
+            ),
+            ParamField::optional(
+                "closest_distance",
+                "Minimum distance between models",
+                ParamSchema::Number,
+            ),
+            ParamField::optional(
+                "generators",
+                "Weighted list of [weight, generator] pairs; defaults to the scene's biome vegetation, or trees",
+                ParamSchema::array(ParamSchema::Tuple(vec![
+                    ParamSchema::integer_range(0, u32::MAX as i64),
+                    ParamSchema::String,
+                ])),
+            ),
+            ParamField::optional(
+                "drop_to_ground",
+                "Place models on top of the terrain",
+                ParamSchema::Bool,
+            ),
+            ParamField::optional(
+                "ground",
+                "How to adapt the terrain under each model: flatten, cut, fill, or foundation",
+                ParamSchema::Enum(&GroundMode::NAMES),
+            ),
+        ])
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let Some([min, max]) = self.count {
+            if min > max {
+                return Err(format!(
+                    "count: minimum {} is greater than maximum {}",
+                    min, max
+                ));
+            }
+        }
+        if let Some(generators) = &self.generators {
+            let total: u64 = generators.iter().map(|(weight, _)| *weight as u64).sum();
+            if generators.is_empty() {
+                return Err("generators: must not be empty".to_string());
+            } else if total == 0 {
+                return Err("generators: needs at least one positive weight".to_string());
+            } else if total > u32::MAX as u64 {
+                return Err(format!("generators: total weight exceeds {}", u32::MAX));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn cluster2(ctx: &GenContext, scene: &mut Scene2) -> Result<Group, Error> {
     let mut rng = ctx.make_rng();
 
     let mut params: ClusterParams = ctx.params();
+    let use_biomes = params.generators.is_none() && scene.biomes.is_some();
     let count_range = params.count.get_or_insert([12, 24]);
     let range = *params.range.get_or_insert(48);
     let genlist = params
@@ -25,6 +89,7 @@ pub fn cluster2(ctx: &GenContext, scene: &mut Scene2) -> Group {
         .clone();
     let closest_distance = *params.closest_distance.get_or_insert(12.0);
     let drop_to_ground = *params.drop_to_ground.get_or_insert(true);
+    let ground = params.ground;
 
     const MAX_ATTEMPTS: usize = 128;
 
@@ -34,6 +99,9 @@ pub fn cluster2(ctx: &GenContext, scene: &mut Scene2) -> Group {
 
     let mut point_set = PointSet::new();
     for _ in 0..MAX_ATTEMPTS {
+        if count == 0 {
+            break;
+        }
         let position = IVec3::new(rng.range(-range..=range), rng.range(-range..=range), 0);
         let mut position = position + ctx.center;
         if drop_to_ground {
@@ -42,8 +110,9 @@ pub fn cluster2(ctx: &GenContext, scene: &mut Scene2) -> Group {
 
         //
         // Reject the position if the nearest distance is too close to another tree
-        // in the cluster (perhaps this should be too close to **any** object?) OR
-        // if the block it would be placed on is marked as occupied already.
+        // in the cluster OR if the block it would be placed on is marked as
+        // occupied already.  Overlap with objects placed by other generators is
+        // checked against the scene's placement index once the model exists.
         //
         let d = point_set.nearest_distance_2d(&position).unwrap_or(f32::MAX);
         if d < closest_distance {
@@ -55,35 +124,65 @@ pub fn cluster2(ctx: &GenContext, scene: &mut Scene2) -> Group {
             }
         }
 
-        point_set.add(position);
-
-        let model_id = rng.select_weighted(&genlist);
+        //
+        // Within a biome, choose from its vegetation and thin out the
+        // candidates in proportion to how sparse it is
+        //
+        let vegetation;
+        let choices = match scene.biome_at(position.x, position.y) {
+            Some(biome) if use_biomes => {
+                if rng.range(0.0..1.0) >= biome.density() {
+                    continue;
+                }
+                vegetation = biome.vegetation();
+                &vegetation
+            }
+            _ => &genlist,
+        };
+        let model_id = rng.select_weighted(choices);
         let seed = rng.seed8();
         let mut ctx = ctx.fork(model_id.clone(), seed);
         ctx.center = position;
 
-        match generate_model(&ctx, scene) {
+        let placed = scene.placements.len();
+        match try_generate_model(&ctx, scene)? {
             VoxelModel::VoxelSet(voxel_set) => {
-                group.push(&ctx, *voxel_set);
+                let mut object = ctx.to_object(*voxel_set);
+                if !scene.place_object(&mut object, &PlacementOptions::default()) {
+                    continue;
+                }
+                if let Some(mode) = ground {
+                    scene.fit_ground(&object, &GroundOptions::default().with_mode(mode));
+                }
+                group.objects.push(object);
             }
             VoxelModel::Group(g) => {
-                group.objects.push(Object {
+                // Members the nested generator placed are checked again as a
+                // unit so the whole group is accepted or rejected together
+                scene.placements.truncate(placed);
+                let mut object = Object {
                     generator_id: ctx.generator.clone(),
                     seed: seed,
                     params: ctx.params.clone(),
                     position: ctx.center.clone(),
+                    orientation: Orientation::default(),
                     imp: ObjectImp::Group(g),
-                });
+                };
+                if !scene.place_object(&mut object, &PlacementOptions::default()) {
+                    continue;
+                }
+                group.objects.push(object);
             }
-            _ => {
-                panic!("Unexpected model type");
+            VoxelModel::Empty => continue,
+            VoxelModel::VoxelScene(_) => {
+                return Err(Error::Generator(format!(
+                    "cluster2: {}: expected a VoxelSet or Group",
+                    model_id
+                )));
             }
         };
-
+        point_set.add(position);
         count -= 1;
-        if count == 0 {
-            break;
-        }
     }
-    group
+    Ok(group)
 }
@@ -1,6 +1,6 @@
 use crate::internal::*;
 
-pub fn desolate_hill(ctx: &GenContext, scene: &mut Scene2) -> Group {
+pub fn desolate_hill(ctx: &GenContext, scene: &mut Scene2) -> Result<Group, Error> {
     let mut rng = ctx.make_rng();
 
     let mut ctx = ctx.fork("hill4", rng.seed8());
@@ -28,7 +28,7 @@ pub fn desolate_hill(ctx: &GenContext, scene: &mut Scene2) -> Group {
                 (10, "bare_tree".to_string()), //
             ],
         });
-        let g: VoxelModel = generate_model(&ctx, scene);
+        let g: VoxelModel = try_generate_model(&ctx, scene)?;
         let VoxelModel::Group(g) = g else {
             panic!("expected group");
         };
@@ -36,5 +36,5 @@ pub fn desolate_hill(ctx: &GenContext, scene: &mut Scene2) -> Group {
             group.objects.push(object);
         }
     }
-    group
+    Ok(group)
 }
@@ -1,14 +1,28 @@
 use crate::internal::*;
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
-struct Params {
+pub(crate) struct Params {
     ground_type: Option<String>,
 }
 
+impl GeneratorParams for Params {
+    fn schema() -> ParamSchema {
+        ParamSchema::Object(vec![ParamField::optional(
+            "ground_type",
+            "\"grass\" or \"dirt\"; defaults to the scene's biomes, if any, or grass",
+            ParamSchema::String,
+        )])
+    }
+}
+
 pub fn flat_ground(ctx: &GenContext, scene: &mut Scene2) -> VoxelSet {
     let mut rng = ctx.make_rng();
 
     let mut params: Params = ctx.params();
+    let biomes = scene
+        .biomes
+        .as_ref()
+        .filter(|_| params.ground_type.is_none());
     let ground_type = params.ground_type.get_or_insert("grass".to_string());
 
     let mut model = VoxelSet::new();
@@ -16,6 +30,13 @@ pub fn flat_ground(ctx: &GenContext, scene: &mut Scene2) -> VoxelSet {
     model.register_block(Block::color("dirt2", 16, 12, 7));
     model.register_block(Block::color("grass1", 5, 60, 10));
     model.register_block(Block::color("grass2", 3, 45, 2));
+    if biomes.is_some() {
+        for biome in Biome::ALL {
+            for block in biome.blocks() {
+                model.register_block(block);
+            }
+        }
+    }
 
     const R: i32 = 256;
 
@@ -26,7 +47,13 @@ pub fn flat_ground(ctx: &GenContext, scene: &mut Scene2) -> VoxelSet {
 
     for y in -R..=R {
         for x in -R..=R {
-            let block = gen_block();
+            let block = match biomes {
+                Some(biomes) => {
+                    let surface = biomes.biome_at(x, y).surface_ids();
+                    surface[rng.range(0..2)]
+                }
+                None => gen_block(),
+            };
             model.set_voxel((x, y, 1), block);
         }
     }
@@ -1,7 +1,7 @@
 use crate::internal::*;
 use bevy_color::{Hsla, Srgba};
 
-pub fn flower_field(ctx: &GenContext, scene: &mut Scene2) -> Group {
+pub fn flower_field(ctx: &GenContext, scene: &mut Scene2) -> Result<Group, Error> {
     let mut rng = ctx.make_rng();
 
     let mut ctx = ctx.fork("cluster2", rng.seed8());
@@ -13,13 +13,13 @@ pub fn flower_field(ctx: &GenContext, scene: &mut Scene2) -> Group {
             (10, "flower_cluster".to_string()), //
         ],
     });
-    let VoxelModel::Group(group) = generate_model(&ctx, scene) else {
+    let VoxelModel::Group(group) = try_generate_model(&ctx, scene)? else {
         panic!("expected group");
     };
-    *group
+    Ok(*group)
 }
 
-pub fn flower_cluster(ctx: &GenContext, scene: &mut Scene2) -> Group {
+pub fn flower_cluster(ctx: &GenContext, scene: &mut Scene2) -> Result<Group, Error> {
     let mut rng = ctx.make_rng();
 
     let mut ctx = ctx.fork("cluster2", rng.seed8());
@@ -31,10 +31,10 @@ pub fn flower_cluster(ctx: &GenContext, scene: &mut Scene2) -> Group {
             (10, "flower".to_string()), //
         ],
     });
-    let VoxelModel::Group(group) = generate_model(&ctx, scene) else {
+    let VoxelModel::Group(group) = try_generate_model(&ctx, scene)? else {
         panic!("expected group");
     };
-    *group
+    Ok(*group)
 }
 
 pub fn flower(ctx: &GenContext, scene: &mut Scene2) -> VoxelSet {
@@ -0,0 +1,38 @@
+use crate::internal::*;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub(crate) struct Params {
+    path: Option<String>,
+    vertical_scale: Option<f32>,
+}
+
+impl GeneratorParams for Params {
+    fn schema() -> ParamSchema {
+        ParamSchema::Object(vec![
+            ParamField::optional(
+                "path",
+                "Heightmap image relative to the asset root",
+                ParamSchema::String,
+            ),
+            ParamField::optional(
+                "vertical_scale",
+                "Height in voxels of a full intensity pixel",
+                ParamSchema::Number,
+            ),
+        ])
+    }
+}
+
+/// Terrain from a grayscale heightmap image in the asset directory.
+pub fn heightmap_terrain(ctx: &GenContext, _scene: &mut Scene2) -> Result<VoxelSet, Error> {
+    let params: Params = ctx.params();
+    let path = params.path.unwrap_or("heightmap.png".to_string());
+
+    let mut options = HeightmapOptions::default();
+    if let Some(vertical_scale) = params.vertical_scale {
+        options = options.with_vertical_scale(vertical_scale);
+    }
+    // The image error already names the file
+    VoxelSet::read_heightmap_file(ctx.asset_root.join(path), &options)
+        .map_err(|e| Error::Generator(format!("heightmap_terrain: {}", e)))
+}
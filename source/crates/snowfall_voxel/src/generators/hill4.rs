@@ -1,14 +1,28 @@
 use crate::internal::*;
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
-struct Params {
+pub(crate) struct Params {
     ground_type: Option<String>,
 }
 
+impl GeneratorParams for Params {
+    fn schema() -> ParamSchema {
+        ParamSchema::Object(vec![ParamField::optional(
+            "ground_type",
+            "\"grass\" or \"dirt\"; defaults to the scene's biomes, if any, or grass",
+            ParamSchema::String,
+        )])
+    }
+}
+
 pub fn hill4(ctx: &GenContext, scene: &mut Scene2) -> VoxelSet {
     let mut rng = ctx.make_rng();
 
     let mut params: Params = ctx.params();
+    let biomes = scene
+        .biomes
+        .as_ref()
+        .filter(|_| params.ground_type.is_none());
     let ground_type = params.ground_type.get_or_insert("grass".to_string());
 
     let mut model = VoxelSet::new();
@@ -16,6 +30,13 @@ pub fn hill4(ctx: &GenContext, scene: &mut Scene2) -> VoxelSet {
     model.register_block(Block::color("dirt2", 16, 12, 7));
     model.register_block(Block::color("grass1", 5, 60, 10));
     model.register_block(Block::color("grass2", 3, 45, 2));
+    if biomes.is_some() {
+        for biome in Biome::ALL {
+            for block in biome.blocks() {
+                model.register_block(block);
+            }
+        }
+    }
 
     use std::f32::consts::PI;
     const R: i32 = 256;
@@ -37,7 +58,14 @@ pub fn hill4(ctx: &GenContext, scene: &mut Scene2) -> VoxelSet {
             let h3 = 64.0 * jitter_radius * (0.5 + 0.5 * jitter_angle.cos());
             let h = h3.powf(1.15).max(1.0);
 
-            for z in 1..=(h.round() as i32) {
+            let top = h.round() as i32;
+            if let Some(biomes) = biomes {
+                let biome = biomes.biome_at(x, y);
+                model.fill_box((x, y, 1), (x, y, top - 1), biome.soil_id());
+                model.set_voxel((x, y, top), biome.surface_ids()[rng.range(0..2)]);
+                continue;
+            }
+            for z in 1..=top {
                 let block = gen_block();
                 model.set_voxel((x, y, z), block);
             }
@@ -1,6 +1,6 @@
 use crate::internal::*;
 
-pub fn hill_with_road(ctx: &GenContext, scene: &mut Scene2) -> Group {
+pub fn hill_with_road(ctx: &GenContext, scene: &mut Scene2) -> Result<Group, Error> {
     let mut rng = ctx.make_rng();
 
     scene.terrain = generators::hill4(&ctx.fork("hill", rng.seed8()), scene);
@@ -10,7 +10,7 @@ pub fn hill_with_road(ctx: &GenContext, scene: &mut Scene2) -> Group {
 
     let mut group = Group::new();
 
-    let model = generate_model(&ctx.fork("flower_field", rng.seed8()), scene);
+    let model = try_generate_model(&ctx.fork("flower_field", rng.seed8()), scene)?;
     group.merge(model);
 
     for _ in 0..4 {
@@ -20,12 +20,12 @@ pub fn hill_with_road(ctx: &GenContext, scene: &mut Scene2) -> Group {
             "count": [120, 400],
             "range": 248,
         });
-        let model = generate_model(&ctx, scene);
+        let model = try_generate_model(&ctx, scene)?;
         group.merge(model);
     }
 
-    let model = generate_model(&ctx.fork("cloud_cluster", rng.seed8()), scene);
+    let model = try_generate_model(&ctx.fork("cloud_cluster", rng.seed8()), scene)?;
     group.merge(model);
 
-    group
+    Ok(group)
 }
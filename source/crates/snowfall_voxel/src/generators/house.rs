@@ -0,0 +1,197 @@
+use crate::internal::*;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub(crate) struct Params {
+    size: Option<[i32; 2]>,
+    wall_height: Option<i32>,
+    wall: Option<String>,
+    roof: Option<String>,
+    roof_style: Option<String>,
+    door: Option<String>,
+    window_spacing: Option<i32>,
+    floor: Option<bool>,
+}
+
+impl GeneratorParams for Params {
+    fn schema() -> ParamSchema {
+        ParamSchema::Object(vec![
+            ParamField::optional(
+                "size",
+                "Outer width and depth of the walls, before the door rotation",
+                ParamSchema::Tuple(vec![ParamSchema::integer_range(5, 64); 2]),
+            ),
+            ParamField::optional(
+                "wall_height",
+                "Height of the walls below the roof",
+                ParamSchema::integer_range(4, 32),
+            ),
+            ParamField::optional("wall", "Wall material", ParamSchema::Enum(&WALL_NAMES)),
+            ParamField::optional("roof", "Roof material", ParamSchema::Enum(&ROOF_NAMES)),
+            ParamField::optional(
+                "roof_style",
+                "\"pitched\" or \"flat\"",
+                ParamSchema::Enum(&["pitched", "flat"]),
+            ),
+            ParamField::optional(
+                "door",
+                "Side of the house the door is on",
+                ParamSchema::Enum(&["south", "east", "north", "west"]),
+            ),
+            ParamField::optional(
+                "window_spacing",
+                "Distance between windows; 0 for no windows",
+                ParamSchema::integer_range(0, 64),
+            ),
+            ParamField::optional(
+                "floor",
+                "Cover the ground inside the walls with a wooden floor",
+                ParamSchema::Bool,
+            ),
+        ])
+    }
+}
+
+const WALL_NAMES: [&str; 3] = ["wood", "stone", "plaster"];
+const WALL_COLORS: [[(u8, u8, u8); 3]; 3] = [
+    [(92, 64, 38), (84, 58, 34), (100, 70, 42)],
+    [(110, 110, 105), (96, 96, 92), (120, 118, 110)],
+    [(200, 190, 170), (190, 180, 160), (205, 196, 178)],
+];
+
+const ROOF_NAMES: [&str; 3] = ["thatch", "shingle", "slate"];
+const ROOF_COLORS: [[(u8, u8, u8); 3]; 3] = [
+    [(150, 125, 60), (140, 115, 55), (160, 132, 66)],
+    [(90, 40, 30), (80, 36, 28), (100, 46, 34)],
+    [(60, 64, 72), (52, 56, 64), (68, 72, 80)],
+];
+
+/// Building with a door, windows, and a pitched or flat roof.  The model
+/// sits on z = 0 and records the space just outside the door, at floor
+/// level, as its `"entrance"` anchor.
+pub fn house(ctx: &GenContext, _scene: &mut Scene2) -> VoxelSet {
+    let mut rng = ctx.make_rng();
+
+    let mut params: Params = ctx.params();
+    let size = *params
+        .size
+        .get_or_insert_with(|| [rng.range(7..=11), rng.range(7..=9)]);
+    let wall_height = *params.wall_height.get_or_insert_with(|| rng.range(4..=5));
+    let wall = params
+        .wall
+        .get_or_insert_with(|| rng.select(&WALL_NAMES.to_vec()).to_string())
+        .clone();
+    let roof = params
+        .roof
+        .get_or_insert_with(|| rng.select(&ROOF_NAMES.to_vec()).to_string())
+        .clone();
+    let pitched = params
+        .roof_style
+        .get_or_insert("pitched".to_string())
+        .as_str()
+        == "pitched";
+    let door = params
+        .door
+        .get_or_insert_with(|| {
+            rng.select(&vec!["south", "east", "north", "west"])
+                .to_string()
+        })
+        .clone();
+    let window_spacing = *params.window_spacing.get_or_insert(3);
+    let floor = *params.floor.get_or_insert(true);
+
+    let mut model = VoxelSet::new();
+    let colors = |names: &[&str], table: &[[(u8, u8, u8); 3]], name: &str| {
+        let i = names.iter().position(|n| *n == name).unwrap_or(0);
+        table[i]
+    };
+    for (i, (r, g, b)) in colors(&WALL_NAMES, &WALL_COLORS, &wall).iter().enumerate() {
+        model.register_block(Block::color(format!("wall{}", i + 1), *r, *g, *b));
+    }
+    for (i, (r, g, b)) in colors(&ROOF_NAMES, &ROOF_COLORS, &roof).iter().enumerate() {
+        model.register_block(Block::color(format!("roof{}", i + 1), *r, *g, *b));
+    }
+    model.register_block(Block::color("trim", 50, 35, 20));
+    model.register_block(Block::color("floor1", 70, 50, 30));
+    model.register_block(Block::color("floor2", 64, 46, 28));
+
+    let mut walls = BlockSelector::uniform(&mut rng, &["wall1", "wall2", "wall3"]);
+    let mut roofing = BlockSelector::uniform(&mut rng, &["roof1", "roof2", "roof3"]);
+    let mut planks = BlockSelector::uniform(&mut rng, &["floor1", "floor2"]);
+    let mut trim = BlockSelector::block("trim");
+    let mut empty = BlockSelector::block("empty");
+
+    // Built with the door on the -Y side, then rotated into place
+    let hx = (size[0] - 1) / 2;
+    let hy = (size[1] - 1) / 2;
+    let h = wall_height;
+    {
+        let mut painter = Painter::new(&mut model);
+
+        if floor {
+            painter.cuboid((-hx + 1, -hy + 1, 0), (hx - 1, hy - 1, 0), &mut planks);
+        }
+
+        painter.set_mask(move |p| p.x.abs() == hx || p.y.abs() == hy);
+        painter.cuboid((-hx, -hy, 0), (hx, hy, h), &mut walls);
+        painter.set_mask(move |p| p.x.abs() == hx && p.y.abs() == hy);
+        painter.cuboid((-hx, -hy, 0), (hx, hy, h), &mut trim);
+
+        // Windows are two voxels tall with a sill, kept clear of the corners
+        // and the door
+        if window_spacing > 0 {
+            let is_window = move |along: i32, half: i32| {
+                along.rem_euclid(window_spacing) == 0 && along.abs() <= half - 2
+            };
+            painter.set_mask(move |p| {
+                let on_x_wall = p.y.abs() == hy && is_window(p.x, hx);
+                let on_y_wall = p.x.abs() == hx && is_window(p.y, hy);
+                let near_door = p.y == -hy && p.x.abs() <= 2;
+                (on_x_wall || on_y_wall) && !near_door
+            });
+            painter.cuboid((-hx, -hy, 2), (hx, hy, 3), &mut empty);
+            painter.cuboid((-hx, -hy, 1), (hx, hy, 1), &mut trim);
+        }
+
+        painter.clear_mask();
+        painter.cuboid((-1, -hy, 0), (1, -hy, 4), &mut trim);
+        painter.cuboid((0, -hy, 1), (0, -hy, 3), &mut empty);
+
+        let (ridge_x, half_span) = if hx >= hy { (true, hy) } else { (false, hx) };
+        let across = move |p: IVec3| if ridge_x { p.y } else { p.x };
+        let top = move |p: IVec3| h + 1 + (half_span + 1 - across(p).abs());
+        let (min, max) = (
+            IVec3::new(-hx - 1, -hy - 1, h),
+            IVec3::new(hx + 1, hy + 1, h + half_span + 2),
+        );
+        if pitched {
+            // Gable ends fill the walls up to the underside of the roof
+            painter.set_mask(move |p| {
+                let end = if ridge_x {
+                    p.x.abs() == hx
+                } else {
+                    p.y.abs() == hy
+                };
+                end && across(p).abs() <= half_span && p.z < top(p) - 1
+            });
+            painter.cuboid(min, max, &mut walls);
+            painter.set_mask(move |p| p.z == top(p) || p.z == top(p) - 1);
+            painter.cuboid(min, max, &mut roofing);
+        } else {
+            painter.clear_mask();
+            painter.cuboid(
+                IVec3::new(min.x, min.y, h + 1),
+                IVec3::new(max.x, max.y, h + 1),
+                &mut roofing,
+            );
+        }
+    }
+    model.set_anchor("entrance", IVec3::new(0, -hy - 1, 0));
+
+    let quarter_turns = match door.as_str() {
+        "east" => 1,
+        "north" => 2,
+        "west" => 3,
+        _ => 0,
+    };
+    model.rotate_z(quarter_turns)
+}
@@ -1,4 +1,5 @@
 mod bare_tree;
+mod biome_hills;
 mod chest;
 mod chest_and_key;
 mod cloud;
@@ -8,21 +9,26 @@ mod desolate_hill;
 mod fence;
 mod flat_ground;
 mod flower;
+mod heightmap_terrain;
 mod hill2;
 mod hill3;
 mod hill4;
 mod hill_with_road;
+mod house;
 mod kestrel;
 mod pine_tree;
 mod road;
 mod rocks;
+mod schematic;
 mod small_hill;
 mod tree1;
 mod tree2;
 mod tree_cluster;
 mod tree_hill;
+mod village;
 
 pub use bare_tree::*;
+pub use biome_hills::*;
 pub use chest::*;
 pub use chest_and_key::*;
 pub use cloud::*;
@@ -32,53 +38,235 @@ pub use desolate_hill::*;
 pub use fence::*;
 pub use flat_ground::*;
 pub use flower::*;
+pub use heightmap_terrain::*;
 pub use hill2::*;
 pub use hill3::*;
 pub use hill4::*;
 pub use hill_with_road::*;
+pub use house::*;
 pub use kestrel::*;
 pub use pine_tree::*;
 pub use road::*;
 pub use rocks::*;
+pub use schematic::*;
 pub use small_hill::*;
 pub use tree1::*;
 pub use tree2::*;
 pub use tree_cluster::*;
 pub use tree_hill::*;
+pub use village::*;
 
 use crate::internal::*;
 
+/// Adds the generators defined in this crate to the registry.
+pub fn register_builtins(registry: GeneratorRegistry) -> GeneratorRegistry {
+    registry
+        .register(
+            FnGenerator::<NoParams>::new("bare_tree", "Leafless tree", |ctx, scene| {
+                Ok(bare_tree(ctx, scene).into())
+            })
+            .with_version(1),
+        )
+        .register(FnGenerator::<NoParams>::new(
+            "biome_hills",
+            "Hills with ground and vegetation that vary by biome",
+            |ctx, scene| biome_hills(ctx, scene).map(Into::into),
+        ))
+        .register(
+            FnGenerator::<NoParams>::new("chest", "Treasure chest", |ctx, scene| {
+                Ok(chest(ctx, scene).into())
+            })
+            .with_version(2),
+        )
+        .register(FnGenerator::<NoParams>::new(
+            "chest_and_key",
+            "Chests on flat ground with keys and kestrels scattered around",
+            |ctx, scene| chest_and_key(ctx, scene).map(Into::into),
+        ))
+        .register(FnGenerator::<NoParams>::new(
+            "chest_cluster",
+            "Ring of chests on flat ground",
+            |ctx, scene| chest_cluster(ctx, scene).map(Into::into),
+        ))
+        .register(
+            FnGenerator::<NoParams>::new("cloud", "Single cloud", |ctx, scene| {
+                Ok(cloud(ctx, scene).into())
+            })
+            .with_version(2),
+        )
+        .register(FnGenerator::<NoParams>::new(
+            "cloud_cluster",
+            "Clouds scattered high above the center",
+            |ctx, scene| Ok(cloud_cluster(ctx, scene).into()),
+        ))
+        .register(FnGenerator::<cluster::ClusterParams>::new(
+            "cluster",
+            "Scene of trees scattered around the center",
+            |ctx, scene| Ok(cluster(ctx, scene).into()),
+        ))
+        .register(FnGenerator::<cluster2::ClusterParams>::new(
+            "cluster2",
+            "Models from weighted generators scattered on the terrain",
+            |ctx, scene| cluster2(ctx, scene).map(Into::into),
+        ))
+        .register(FnGenerator::<NoParams>::new(
+            "desolate_hill",
+            "Hill with bare trees",
+            |ctx, scene| desolate_hill(ctx, scene).map(Into::into),
+        ))
+        .register(FnGenerator::<NoParams>::new(
+            "fence",
+            "Fence loop following the terrain",
+            |ctx, scene| Ok(fence(ctx, scene).into()),
+        ))
+        // flat_ground and hill4 read the scene's biomes, so they are left
+        // unversioned to keep them out of the cache
+        .register(FnGenerator::<flat_ground::Params>::new(
+            "flat_ground",
+            "Flat plane of grass or dirt",
+            |ctx, scene| Ok(flat_ground(ctx, scene).into()),
+        ))
+        .register(
+            FnGenerator::<NoParams>::new("flower", "Single flower", |ctx, scene| {
+                Ok(flower(ctx, scene).into())
+            })
+            .with_version(1),
+        )
+        .register(FnGenerator::<NoParams>::new(
+            "flower_cluster",
+            "Small cluster of flowers",
+            |ctx, scene| flower_cluster(ctx, scene).map(Into::into),
+        ))
+        .register(FnGenerator::<NoParams>::new(
+            "flower_field",
+            "Flower clusters spread over a wide area",
+            |ctx, scene| flower_field(ctx, scene).map(Into::into),
+        ))
+        .register(FnGenerator::<heightmap_terrain::Params>::new(
+            "heightmap_terrain",
+            "Terrain from a 16-bit grayscale heightmap image",
+            |ctx, scene| heightmap_terrain(ctx, scene).map(Into::into),
+        ))
+        .register(FnGenerator::<NoParams>::new(
+            "hill_with_road",
+            "Hill with roads, flowers, trees, and clouds",
+            |ctx, scene| hill_with_road(ctx, scene).map(Into::into),
+        ))
+        .register(FnGenerator::<NoParams>::new(
+            "hill2",
+            "Hill on top of the terrain",
+            |ctx, scene| Ok(hill2(ctx, scene).into()),
+        ))
+        .register(FnGenerator::<NoParams>::new(
+            "hill3",
+            "Hill on top of the terrain",
+            |ctx, scene| Ok(hill3(ctx, scene).into()),
+        ))
+        .register(FnGenerator::<hill4::Params>::new(
+            "hill4",
+            "Large hill terrain",
+            |ctx, scene| Ok(hill4(ctx, scene).into()),
+        ))
+        .register(
+            FnGenerator::<house::Params>::new(
+                "house",
+                "Hut or house with a door, windows, and a pitched or flat roof",
+                |ctx, scene| Ok(house(ctx, scene).into()),
+            )
+            .with_version(1),
+        )
+        .register(FnGenerator::<chest_and_key::Params>::new(
+            "key",
+            "Key sprite",
+            |ctx, scene| key(ctx, scene).map(Into::into),
+        ))
+        .register(FnGenerator::<kestrel::Params>::new(
+            "kestrel",
+            "Kestrel sprite",
+            |ctx, scene| kestrel(ctx, scene).map(Into::into),
+        ))
+        .register(
+            FnGenerator::<NoParams>::new("pine_tree", "Pine tree", |ctx, scene| {
+                Ok(pine_tree(ctx, scene).into())
+            })
+            .with_version(1),
+        )
+        .register(FnGenerator::<NoParams>::new(
+            "road",
+            "Road path-found across the terrain",
+            |ctx, scene| Ok(road(ctx, scene)),
+        ))
+        .register(FnGenerator::<NoParams>::new(
+            "rocks",
+            "Rocks scattered on the terrain",
+            |ctx, scene| Ok(rocks(ctx, scene)),
+        ))
+        .register(FnGenerator::<schematic::Params>::new(
+            "schematic",
+            "Model loaded from a YAML schematic file",
+            |ctx, scene| schematic(ctx, scene).map(Into::into),
+        ))
+        .register(FnGenerator::<NoParams>::new(
+            "small_hill",
+            "Small hill on top of the terrain",
+            |ctx, scene| Ok(small_hill(ctx, scene).into()),
+        ))
+        .register(FnGenerator::<NoParams>::new(
+            "tree_cluster",
+            "Scene of trees scattered around the origin",
+            |ctx, scene| Ok(tree_cluster(ctx, scene).into()),
+        ))
+        .register(FnGenerator::<NoParams>::new(
+            "tree_hill",
+            "Scene of a small hill covered in trees",
+            |ctx, scene| Ok(tree_hill(ctx, scene).into()),
+        ))
+        .register(
+            FnGenerator::<NoParams>::new("tree1", "Leafy tree", |ctx, scene| {
+                Ok(tree1(ctx, scene).into())
+            })
+            .with_version(1),
+        )
+        .register(
+            FnGenerator::<NoParams>::new("tree2", "Leafy tree", |ctx, scene| {
+                Ok(tree2(ctx, scene).into())
+            })
+            .with_version(1),
+        )
+        .register(FnGenerator::<village::Params>::new(
+            "village",
+            "Houses around a village square, connected by paths",
+            |ctx, scene| Ok(village(ctx, scene).into()),
+        ))
+}
+
+/// Runs the generator named in the context, using the context's cache (if
+/// any) for cacheable generators.  Panics if the generator is not
+/// registered, the params are invalid, or the generator fails; code that
+/// runs user-specified generators should use try_generate_model.
 pub fn generate_model(ctx: &GenContext, scene: &mut Scene2) -> VoxelModel {
-    let seed = ctx.seed;
-    match ctx.generator.as_str() {
-        "bare_tree" => bare_tree(ctx, scene).into(),
-        "chest" => chest(ctx, scene).into(),
-        "chest_cluster" => chest_cluster(ctx, scene).into(),
-        "cloud_cluster" => cloud_cluster(ctx, scene).into(),
-        "chest_and_key" => chest_and_key(ctx, scene).into(),
-        "flat_ground" => flat_ground(ctx, scene).into(),
-        "cloud" => cloud(ctx, scene).into(),
-        "cluster" => cluster(seed, ctx).into(),
-        "cluster2" => cluster2(ctx, scene).into(),
-        "desolate_hill" => desolate_hill(ctx, scene).into(),
-        "fence" => fence(ctx, scene).into(),
-        "flower_cluster" => flower_cluster(ctx, scene).into(),
-        "flower_field" => flower_field(ctx, scene).into(),
-        "flower" => flower(ctx, scene).into(),
-        "hill_with_road" => hill_with_road(ctx, scene).into(),
-        "hill2" => hill2(ctx, scene).into(),
-        "hill3" => hill3(ctx, scene).into(),
-        "hill4" => hill4(ctx, scene).into(),
-        "pine_tree" => pine_tree(ctx, scene).into(),
-        "road" => road(ctx, scene),
-        "rocks" => rocks(ctx, scene),
-        "small_hill" => small_hill(ctx, scene).into(),
-        "tree_cluster" => tree_cluster(seed).into(),
-        "tree_hill" => tree_hill(seed, scene).into(),
-        "tree1" => tree1(ctx, scene).into(),
-        "tree2" => tree2(ctx, scene).into(),
-        _ => {
-            panic!("unknown generator: {}", ctx.generator);
+    try_generate_model(ctx, scene).unwrap_or_else(|e| panic!("{}", e))
+}
+
+pub fn try_generate_model(ctx: &GenContext, scene: &mut Scene2) -> Result<VoxelModel, Error> {
+    let generator = ctx
+        .registry
+        .get(&ctx.generator)
+        .ok_or_else(|| Error::Generator(format!("unknown generator '{}'", ctx.generator)))?;
+    generator.validate_params(&ctx.params)?;
+
+    let Some(cache) = ctx.cache.as_ref() else {
+        return generator.generate(ctx, scene);
+    };
+    if let Some(model) = cache.get(ctx) {
+        return Ok(model.into());
+    }
+
+    let model = generator.generate(ctx, scene)?;
+    if let VoxelModel::VoxelSet(voxel_set) = &model {
+        if let Err(e) = cache.put(ctx, voxel_set) {
+            log::warn!("Failed to cache {}: {}", ctx.generator, e);
         }
     }
+    Ok(model)
 }
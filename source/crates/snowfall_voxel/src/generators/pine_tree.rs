@@ -11,37 +11,29 @@ pub fn pine_tree(ctx: &GenContext, scene: &Scene2) -> VoxelSet {
     model.register_block(Block::color("wood2", 26, 28, 28));
     model.register_block(Block::color("wood3", 36, 38, 31));
 
-    const R: i32 = 8;
     let base_height: i32 = rng.range(8..=12);
     let cone_height: i32 = base_height + rng.range(4..=16);
     let girth: f32 = rng.range(0.5..=0.75);
 
-    let mut leaf_select = rng.select_fn(vec!["leaves", "leaves2", "leaves3"]);
-    let mut wood_select = rng.select_fn(vec!["wood", "wood2", "wood3"]);
+    let mut leaves = BlockSelector::uniform(&mut rng, &["leaves", "leaves2", "leaves3"]);
+    let mut wood = BlockSelector::uniform(&mut rng, &["wood", "wood2", "wood3"]);
 
-    let base_z = 0;
-    for z in base_z..=base_z + base_height {
-        model.set_voxel((0, 0, z), wood_select());
-    }
-
-    for z in 0..=cone_height {
-        let r = (cone_height - z) + 1;
-        let r = (r as f32).powf(girth).ceil() as i32;
-        for y in -r..=r {
-            for x in -r..=r {
-                let d = (x.pow(2) + y.pow(2)) as f32;
-                if d > r as f32 * r as f32 {
-                    continue;
-                }
-                let p = ((x.abs() % 2) + (y.abs() % 2)) == 1;
-                if p == ((z.abs() % 2) == 1) {
-                    continue;
-                }
+    {
+        let mut painter = Painter::new(&mut model);
+        painter.line(IVec3::ZERO, IVec3::new(0, 0, base_height), 0.0, &mut wood);
 
-                let block = leaf_select();
-                model.set_voxel((x, y, z + base_height + base_z), block);
-            }
-        }
+        // Leaves are checkerboarded, alternating on each layer
+        painter.set_mask(move |p| {
+            let odd_layer = (p.z - base_height).abs() % 2 == 1;
+            ((p.x.abs() % 2) + (p.y.abs() % 2) == 1) != odd_layer
+        });
+        painter.column(
+            IVec3::new(0, 0, base_height),
+            cone_height + 1,
+            |i| ((cone_height - i + 1) as f32).powf(girth).ceil(),
+            &mut leaves,
+        );
     }
+
     model
 }
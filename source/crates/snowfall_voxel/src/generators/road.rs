@@ -20,7 +20,7 @@ fn road_imp(ctx: &GenContext, scene: &mut Scene2) -> Result<(), String> {
     model.register_block(Block::color("road1", 25, 20, 10).modify(|b| b.walk_cost = 0.15));
     model.register_block(Block::color("road2", 20, 15, 10).modify(|b| b.walk_cost = 0.15));
 
-    let mut road_block = rng.select_fn(vec!["road1", "road2"]);
+    let mut road_block = BlockSelector::uniform(&mut rng, &["road1", "road2"]);
 
     //
     // Choose the start and end points of the road segment.
@@ -45,16 +45,49 @@ fn road_imp(ctx: &GenContext, scene: &mut Scene2) -> Result<(), String> {
     end.0 = end.0.max(-254).min(254);
     end.1 = end.1.max(-254).min(254);
 
+    //
+    // Path-find the connection from start to end.
+    //
+    let path = find_walk_path(
+        &scene.terrain,
+        IVec3::new(start.0, start.1, start.2),
+        IVec3::new(end.0, end.1, end.2),
+        |_, _| false,
+    )
+    .ok_or_else(|| "No path found".to_string())?;
+
+    pave_road(&mut scene.terrain, &path, 3, &mut road_block);
+    Ok(())
+}
+
+/// Finds a walkable path over the terrain surface from `start` to the column
+/// at `end` using A*.  Steps cost more the steeper they are and the higher
+/// the `walk_cost` of the block stepped onto, so existing roads are
+/// preferred.  Columns for which `blocked(x, y)` is true are never entered.
+///
+/// Returns None if the search gives up without finding a path.
+pub fn find_walk_path<F>(
+    terrain: &VoxelSet,
+    start: IVec3,
+    end: IVec3,
+    blocked: F,
+) -> Option<Vec<IVec3>>
+where
+    F: Fn(i32, i32) -> bool,
+{
+    const MAX_ITERATIONS: usize = 1_000_000;
+
+    let start = (start.x, start.y, start.z);
+    let end = (end.x, end.y, end.z);
+
     //
     // Cache the height look-ups since there are many look-ups
     //
     let mut cache: HashMap<(i32, i32), i32> = HashMap::new();
 
-    // Call the A* pathfinding algorithm to generate the connection
-    // from start to end.
-    //
     // Be wary:
-    // - If no path exists, astar() never returns (!)
+    // - If no path exists, astar() only returns once the iteration limit is
+    //   reached and no more successors are produced
     // - If the heuristic is *less expensive* than the actual
     //   lowest cost path, then the algorithm breaks
     //
@@ -62,7 +95,12 @@ fn road_imp(ctx: &GenContext, scene: &mut Scene2) -> Result<(), String> {
     let result = pathfinding::prelude::astar(
         &start,
         |&(x, y, _z)| {
-            let moves = vec![
+            iterations += 1;
+            if iterations > MAX_ITERATIONS {
+                return vec![].into_iter();
+            }
+
+            let moves = [
                 (1, 0), //
                 (-1, 0),
                 (0, 1),
@@ -83,15 +121,18 @@ fn road_imp(ctx: &GenContext, scene: &mut Scene2) -> Result<(), String> {
                 .filter_map(|&(dx, dy)| {
                     let new_x = x + dx;
                     let new_y = y + dy;
+                    if blocked(new_x, new_y) {
+                        return None;
+                    }
                     let z: i32 = *cache
                         .entry((x, y))
-                        .or_insert_with(|| model.height_at(x, y).unwrap_or(0));
+                        .or_insert_with(|| terrain.height_at(x, y).unwrap_or(0));
                     let new_z: i32 = *cache
                         .entry((new_x, new_y))
-                        .or_insert_with(|| model.height_at(new_x, new_y).unwrap_or(0));
+                        .or_insert_with(|| terrain.height_at(new_x, new_y).unwrap_or(0));
                     let dz = new_z - z;
 
-                    let block = model.get_voxel((new_x, new_y, new_z));
+                    let block = terrain.get_voxel((new_x, new_y, new_z));
                     let walk_cost = block.walk_cost;
 
                     let distance_xy: f32 = ((dx.pow(2) + dy.pow(2)) as f32).sqrt();
@@ -123,11 +164,6 @@ fn road_imp(ctx: &GenContext, scene: &mut Scene2) -> Result<(), String> {
                 })
                 .collect::<Vec<_>>();
 
-            iterations += 1;
-            if iterations > 1_000_000 {
-                panic!("too many iterations");
-            }
-
             costs.into_iter()
         },
         |&(x, y, z)| {
@@ -137,24 +173,41 @@ fn road_imp(ctx: &GenContext, scene: &mut Scene2) -> Result<(), String> {
         |&p| p.0 == end.0 && p.1 == end.1,
     );
 
+    let (path, _cost) = result?;
+    Some(
+        path.into_iter()
+            .map(|(x, y, z)| IVec3::new(x, y, z))
+            .collect(),
+    )
+}
+
+/// Paves a road `radius` columns wide along the path, flattening the terrain
+/// above it.  The road and a margin around it are marked as occupied so
+/// other objects are not placed on it.
+pub fn pave_road(
+    terrain: &mut VoxelSet,
+    path: &[IVec3],
+    radius: i32,
+    road_block: &mut BlockSelector,
+) {
+    if path.is_empty() {
+        return;
+    }
+
     //
     // Segmentize.
     //
     // Given the found path, break it into a series of straight line segments
     // which "feels" more natural of a constructed road than exact path finding
     //
-    let path = match result {
-        Some((path, _cost)) => path,
-        None => {
-            return Err("No path found".to_string());
-        }
-    };
-
     let mut posts = Vec::new();
-    for i in (0..path.len() - 6).step_by(12) {
+    for i in (0..path.len().saturating_sub(6)).step_by(12) {
         posts.push(path[i]);
     }
     posts.push(path[path.len() - 1]);
+    if posts.len() == 1 {
+        posts.insert(0, path[0]);
+    }
 
     //
     // Connect the segments.
@@ -162,44 +215,38 @@ fn road_imp(ctx: &GenContext, scene: &mut Scene2) -> Result<(), String> {
     // Do this by flattening the terrain in a brief radius around the
     // path and painting the ground voxels to the road color.
     //
+    let r = radius;
+    let margin = radius + 4;
     for pair in posts.windows(2) {
-        let p = IVec3::new(pair[0].0, pair[0].1, pair[0].2);
-        let q = IVec3::new(pair[1].0, pair[1].1, pair[1].2);
-
-        const R: i32 = 3;
-        const R2: i32 = R + 4;
-
-        let line = bresenham3d(p, q);
+        let line = bresenham3d(pair[0], pair[1]);
         for IVec3 { x, y, z } in &line {
-            for dx in -R..=R {
-                for dy in -R..=R {
+            for dx in -r..=r {
+                for dy in -r..=r {
                     for dz in 1..=12 {
-                        model.clear_voxel((x + dx, y + dy, z + dz));
+                        terrain.clear_voxel((x + dx, y + dy, z + dz));
                     }
                 }
             }
         }
-        for IVec3 { x, y, z } in &line {
-            for dx in -R..=R {
-                for dy in -R..=R {
-                    let z = model.height_at(x + dx, y + dy).unwrap_or(0);
-                    let p = (x + dx, y + dy, z);
-                    let block = road_block();
-                    model.set_voxel(p, block);
+        for IVec3 { x, y, .. } in &line {
+            for dx in -r..=r {
+                for dy in -r..=r {
+                    let z = terrain.height_at(x + dx, y + dy).unwrap_or(0);
+                    let p = IVec3::new(x + dx, y + dy, z);
+                    terrain.set_voxel(p, road_block.select(p));
                 }
             }
         }
 
         // Mark the road and area around it as "occupied" so other objects
         // are not placed on top of it.
-        for IVec3 { x, y, z } in &line {
-            for dx in -R2..=R2 {
-                for dy in -R2..=R2 {
-                    let z = model.height_at(x + dx, y + dy).unwrap_or(0);
-                    model.modify_voxel((x + dx, y + dy, z), |block| block.with_occupied(true));
+        for IVec3 { x, y, .. } in &line {
+            for dx in -margin..=margin {
+                for dy in -margin..=margin {
+                    let z = terrain.height_at(x + dx, y + dy).unwrap_or(0);
+                    terrain.modify_voxel((x + dx, y + dy, z), |block| block.with_occupied(true));
                 }
             }
         }
     }
-    Ok(())
 }
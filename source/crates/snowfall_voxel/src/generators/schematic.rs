@@ -0,0 +1,24 @@
+use crate::internal::*;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub(crate) struct Params {
+    path: String,
+}
+
+impl GeneratorParams for Params {
+    fn schema() -> ParamSchema {
+        ParamSchema::Object(vec![ParamField::required(
+            "path",
+            "Schematic file relative to the asset root",
+            ParamSchema::String,
+        )])
+    }
+}
+
+/// Loads a hand-authored YAML schematic from the asset directory.
+pub fn schematic(ctx: &GenContext, _scene: &mut Scene2) -> Result<VoxelSet, Error> {
+    let params: Params = ctx.params();
+    let path = ctx.asset_root.join(params.path);
+    VoxelSet::read_schematic_file(&path.to_string_lossy())
+        .map_err(|e| Error::Generator(format!("schematic: {}: {}", path.display(), e)))
+}
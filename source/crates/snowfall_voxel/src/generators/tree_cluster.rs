@@ -1,7 +1,7 @@
 use crate::internal::*;
 
-pub fn tree_cluster(seed: u64) -> VoxelScene {
-    let mut rng = RNG::new(seed);
+pub fn tree_cluster(ctx: &GenContext, _scene: &mut Scene2) -> VoxelScene {
+    let mut rng = ctx.make_rng();
 
     const MAX_ATTEMPTS: usize = 128;
     const CLOSEST_DISTANCE: f32 = 12.0;
@@ -33,6 +33,7 @@ pub fn tree_cluster(seed: u64) -> VoxelScene {
                 seed,
                 position,
                 params: serde_json::Value::Null,
+                orientation: Orientation::default(),
             },
         );
         count -= 1;
@@ -1,13 +1,15 @@
 use crate::internal::*;
 
-pub fn tree_hill(seed: u64, scene: &Scene2) -> VoxelScene {
-    let mut rng = RNG::new(seed);
+pub fn tree_hill(ctx: &GenContext, scene: &mut Scene2) -> VoxelScene {
+    let mut rng = ctx.make_rng();
 
     let hill_seed = rng.range(1..8192);
     let tree_cluster_seed = rng.range(1..8192);
 
-    let ctx = GenContext::new("small_hill", hill_seed);
-    let hill = generators::small_hill(&ctx, scene);
+    let hill = generators::small_hill(&ctx.fork("small_hill", hill_seed), scene);
+    let tree_cluster =
+        generators::tree_cluster(&ctx.fork("tree_cluster", tree_cluster_seed), scene);
+
     let mut scene = VoxelScene::new();
     scene.add_object(
         0,
@@ -16,10 +18,10 @@ pub fn tree_hill(seed: u64, scene: &Scene2) -> VoxelScene {
             seed: hill_seed,
             position: IVec3::new(0, 0, 0),
             params: serde_json::Value::Null,
+            orientation: Orientation::default(),
         },
     );
 
-    let tree_cluster = generators::tree_cluster(tree_cluster_seed);
     for object in tree_cluster.layers[0].models.iter() {
         let mut p = object.position.clone();
         let z = hill.height_at(p.x, p.y).unwrap_or(0);
@@ -32,6 +34,7 @@ pub fn tree_hill(seed: u64, scene: &Scene2) -> VoxelScene {
                 seed: object.seed,
                 position: p,
                 params: serde_json::Value::Null,
+                orientation: object.orientation,
             },
         );
     }
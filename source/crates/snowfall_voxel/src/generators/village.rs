@@ -0,0 +1,388 @@
+use crate::internal::*;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub(crate) struct Params {
+    houses: Option<[i64; 2]>,
+    radius: Option<i32>,
+    feature: Option<String>,
+    fences: Option<bool>,
+}
+
+impl GeneratorParams for Params {
+    fn schema() -> ParamSchema {
+        ParamSchema::Object(vec![
+            ParamField::optional(
+                "houses",
+                "Minimum and maximum number of houses",
+                ParamSchema::Tuple(vec![ParamSchema::integer_range(0, 64); 2]),
+            ),
+            ParamField::optional(
+                "radius",
+                "Maximum distance of houses from the center",
+                ParamSchema::integer_range(16, 200),
+            ),
+            ParamField::optional(
+                "feature",
+                "What stands in the village square",
+                ParamSchema::Enum(&["well", "tree"]),
+            ),
+            ParamField::optional(
+                "fences",
+                "Give some of the houses a fenced yard behind them",
+                ParamSchema::Bool,
+            ),
+        ])
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match self.houses {
+            Some([min, max]) if min > max => Err(format!(
+                "houses: minimum {} is greater than maximum {}",
+                min, max
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Label of the placements that keep the space in front of each door clear
+const ENTRANCE: &str = "entrance";
+
+/// Houses laid out around a village square, with paths from every door to
+/// the square.  The village is built on the scene's terrain, or on a small
+/// meadow if the scene has no terrain yet.
+pub fn village(ctx: &GenContext, scene: &mut Scene2) -> Group {
+    let mut rng = ctx.make_rng();
+
+    let mut params: Params = ctx.params();
+    let house_range = *params.houses.get_or_insert([5, 8]);
+    let radius = *params.radius.get_or_insert(40);
+    let feature = params
+        .feature
+        .get_or_insert_with(|| rng.select(&vec!["well", "tree"]).to_string())
+        .clone();
+    let fences = *params.fences.get_or_insert(true);
+
+    if scene.terrain.bounds().is_empty() {
+        meadow(&mut rng, &mut scene.terrain, ctx.center, radius + 24);
+    }
+    scene
+        .terrain
+        .register_block(Block::color("cobble1", 96, 92, 84).modify(|b| b.walk_cost = 0.15));
+    scene
+        .terrain
+        .register_block(Block::color("cobble2", 84, 80, 74).modify(|b| b.walk_cost = 0.15));
+    scene
+        .terrain
+        .register_block(Block::color("path1", 60, 48, 30).modify(|b| b.walk_cost = 0.15));
+    scene
+        .terrain
+        .register_block(Block::color("path2", 54, 43, 27).modify(|b| b.walk_cost = 0.15));
+    let mut cobble = BlockSelector::uniform(&mut rng, &["cobble1", "cobble2"]);
+    let mut path_block = BlockSelector::uniform(&mut rng, &["path1", "path2"]);
+
+    let mut group = Group::new();
+
+    //
+    // The square: level ground paved around the central feature
+    //
+    let center = {
+        let z = scene
+            .terrain
+            .height_at(ctx.center.x, ctx.center.y)
+            .unwrap_or(0);
+        IVec3::new(ctx.center.x, ctx.center.y, z + 1)
+    };
+    let mut object = {
+        let ctx = ctx.fork(feature.as_str(), rng.seed8()).with_center(center);
+        let model = match feature.as_str() {
+            "tree" => generators::tree1(&ctx, scene),
+            _ => well(&ctx),
+        };
+        ctx.to_object(model)
+    };
+    let bounds = object.oriented_voxel_set().unwrap().bounds();
+    let square_radius = [-bounds.min.x, -bounds.min.y, bounds.max.x, bounds.max.y]
+        .into_iter()
+        .max()
+        .unwrap()
+        + 4;
+
+    let min = center.truncate() - IVec2::splat(square_radius);
+    let max = center.truncate() + IVec2::splat(square_radius);
+    scene.level_ground(min, max, center.z - 1, &GroundOptions::default());
+    let mut network = Vec::new();
+    for y in min.y..=max.y {
+        for x in min.x..=max.x {
+            let d = IVec2::new(x, y) - center.truncate();
+            let d2 = d.length_squared();
+            if d2 > square_radius * square_radius {
+                continue;
+            }
+            let p = IVec3::new(x, y, center.z - 1);
+            scene.terrain.set_voxel(p, cobble.select(p));
+            scene.terrain.modify_voxel(p, |b| b.with_occupied(true));
+            if d2 > (square_radius - 1) * (square_radius - 1) {
+                network.push(p);
+            }
+        }
+    }
+    if scene.place_object(&mut object, &PlacementOptions::default()) {
+        group.objects.push(object);
+    }
+
+    //
+    // Houses facing the square, each on leveled ground
+    //
+    const MAX_ATTEMPTS: usize = 64;
+
+    let mut entrances = Vec::new();
+    let mut count = rng.range(house_range[0]..=house_range[1]);
+    let inner = (square_radius + 8) as f32;
+    let outer = (radius as f32).max(inner + 1.0);
+    for _ in 0..MAX_ATTEMPTS {
+        if count <= 0 {
+            break;
+        }
+
+        let angle = rng.radians();
+        let r = rng.range(inner..outer);
+        let x = center.x + (r * angle.cos()).round() as i32;
+        let y = center.y + (r * angle.sin()).round() as i32;
+        let Some(z) = scene.terrain.height_at(x, y) else {
+            continue;
+        };
+        let position = IVec3::new(x, y, z + 1);
+
+        let to_center = center - position;
+        let door = if to_center.x.abs() > to_center.y.abs() {
+            if to_center.x > 0 {
+                "east"
+            } else {
+                "west"
+            }
+        } else if to_center.y > 0 {
+            "north"
+        } else {
+            "south"
+        };
+        let ctx = ctx
+            .fork("house", rng.seed8())
+            .with_center(position)
+            .with_params(serde_json::json!({ "door": door }));
+        let model = generators::house(&ctx, scene);
+        let Some(entrance) = model.anchor("entrance") else {
+            continue;
+        };
+
+        let mut object = ctx.to_object(model);
+        let footprint = object.footprint();
+        if footprint.iter().any(|b| is_occupied(&scene.terrain, b)) {
+            continue;
+        }
+        let options = PlacementOptions::default().with_margin(3).with_max_nudge(2);
+        if !scene.place_object(&mut object, &options) {
+            continue;
+        }
+        scene.fit_ground(&object, &GroundOptions::default());
+
+        // Keep the doorstep clear of anything placed later
+        let entrance = object.position + entrance;
+        let outward = (entrance - object.position).truncate().signum();
+        let step = entrance.truncate() + outward;
+        scene.placements.reserve(
+            step.min(entrance.truncate()) - IVec2::ONE,
+            step.max(entrance.truncate()) + IVec2::ONE,
+            ENTRANCE,
+        );
+        entrances.push((entrance, outward));
+
+        if fences && rng.bool() {
+            if let Some(yard) = yard(ctx.fork("yard", rng.seed8()), scene, &object, outward) {
+                group.objects.push(yard);
+            }
+        }
+        group.objects.push(object);
+        count -= 1;
+    }
+
+    //
+    // Paths from each door to the nearest point of the square or of an
+    // earlier path, nearest doors first.  Paths are cheaper to walk than
+    // the surrounding ground, so later paths tend to join earlier ones.
+    //
+    let obstacles: Vec<IBox3> = scene
+        .placements
+        .iter()
+        .filter(|p| p.label != ENTRANCE)
+        .map(|p| p.bounds)
+        .collect();
+    let blocked = |x: i32, y: i32| {
+        obstacles
+            .iter()
+            .any(|b| x >= b.min.x - 1 && x <= b.max.x + 1 && y >= b.min.y - 1 && y <= b.max.y + 1)
+    };
+
+    entrances.sort_by_key(|(p, _)| (*p - center).truncate().length_squared());
+    for (entrance, outward) in entrances {
+        // The doorstep is under the eaves, so step out to open ground first
+        let ground = |p: IVec2| {
+            let z = scene.terrain.height_at(p.x, p.y).unwrap_or(0);
+            p.extend(z)
+        };
+        let mut doorstep = vec![ground(entrance.truncate())];
+        let mut p = entrance.truncate();
+        while blocked(p.x, p.y) && doorstep.len() < 4 {
+            p += outward;
+            doorstep.push(ground(p));
+        }
+        let start = *doorstep.last().unwrap();
+
+        let Some(end) = network
+            .iter()
+            .filter(|p| !blocked(p.x, p.y))
+            .min_by_key(|p| (**p - start).truncate().length_squared())
+            .copied()
+        else {
+            continue;
+        };
+        let Some(path) = generators::find_walk_path(&scene.terrain, start, end, blocked) else {
+            continue;
+        };
+        let path: Vec<IVec3> = doorstep
+            .into_iter()
+            .chain(path.into_iter().skip(1))
+            .collect();
+        generators::pave_road(&mut scene.terrain, &path, 1, &mut path_block);
+        network.extend(path);
+    }
+
+    group
+}
+
+/// Returns true if the top block of any column in the bounds is occupied.
+fn is_occupied(terrain: &VoxelSet, bounds: &IBox3) -> bool {
+    (bounds.min.y..=bounds.max.y).any(|y| {
+        (bounds.min.x..=bounds.max.x)
+            .any(|x| terrain.top_block_at(x, y).is_some_and(|b| b.occupied))
+    })
+}
+
+/// Gently rolling grass centered on `center`.
+fn meadow(rng: &mut RNG, terrain: &mut VoxelSet, center: IVec3, radius: i32) {
+    terrain.register_block(Block::color("dirt1", 10, 8, 4));
+    terrain.register_block(Block::color("grass1", 5, 60, 10));
+    terrain.register_block(Block::color("grass2", 3, 45, 2));
+
+    let mut grass = BlockSelector::uniform(rng, &["grass1", "grass2"]);
+    let noise = rng.open_simplex().scale(48.0).build();
+    for y in -radius..=radius {
+        for x in -radius..=radius {
+            let (x, y) = (center.x + x, center.y + y);
+            let h = center.z + (6.0 * noise.gen_2d(x as f32, y as f32)).round() as i32;
+            terrain.fill_box((x, y, h - 2), (x, y, h - 1), "dirt1");
+            terrain.set_voxel((x, y, h), grass.select(IVec3::new(x, y, h)));
+        }
+    }
+}
+
+/// Stone well with a small roof on two posts.
+fn well(ctx: &GenContext) -> VoxelSet {
+    let mut rng = ctx.make_rng();
+
+    let mut model = VoxelSet::new();
+    model.register_block(Block::color("stone1", 110, 110, 105));
+    model.register_block(Block::color("stone2", 96, 96, 92));
+    model.register_block(Block::color("water", 40, 70, 140));
+    model.register_block(Block::color("wood", 70, 50, 30));
+    model.register_block(Block::color("roof", 90, 40, 30));
+
+    let mut stone = BlockSelector::uniform(&mut rng, &["stone1", "stone2"]);
+    let mut water = BlockSelector::block("water");
+    let mut wood = BlockSelector::block("wood");
+    let mut roof = BlockSelector::block("roof");
+    let mut empty = BlockSelector::block("empty");
+    {
+        let mut painter = Painter::new(&mut model);
+        painter.cylinder(IVec3::ZERO, 2.5, 3, &mut stone);
+        painter.cylinder(IVec3::new(0, 0, 1), 1.5, 2, &mut empty);
+        painter.cylinder(IVec3::ZERO, 1.5, 1, &mut water);
+        painter.cuboid((-2, 0, 3), (-2, 0, 5), &mut wood);
+        painter.cuboid((2, 0, 3), (2, 0, 5), &mut wood);
+        painter.cuboid((-3, -1, 6), (3, 1, 6), &mut roof);
+        painter.cuboid((-3, 0, 7), (3, 0, 7), &mut roof);
+    }
+    model
+}
+
+/// Fenced yard behind the house, on the side away from its door.  The fence
+/// follows the terrain and is open toward the house.
+fn yard(ctx: GenContext, scene: &mut Scene2, house: &Object, outward: IVec2) -> Option<Object> {
+    let mut rng = ctx.make_rng();
+
+    let house_bounds = house.footprint()[0];
+    let (hmin, hmax) = (house_bounds.min.truncate(), house_bounds.max.truncate());
+    let depth = rng.range(5..=8);
+    let back = -outward;
+    let (min, max) = match (back.x, back.y) {
+        (1, _) => (
+            IVec2::new(hmax.x + 1, hmin.y),
+            IVec2::new(hmax.x + depth, hmax.y),
+        ),
+        (-1, _) => (
+            IVec2::new(hmin.x - depth, hmin.y),
+            IVec2::new(hmin.x - 1, hmax.y),
+        ),
+        (_, 1) => (
+            IVec2::new(hmin.x, hmax.y + 1),
+            IVec2::new(hmax.x, hmax.y + depth),
+        ),
+        _ => (
+            IVec2::new(hmin.x, hmin.y - depth),
+            IVec2::new(hmax.x, hmin.y - 1),
+        ),
+    };
+
+    let mut model = VoxelSet::new();
+    model.register_block(Block::color("wood1", 30, 12, 5));
+    model.register_block(Block::color("wood2", 22, 11, 8));
+    let mut wood = BlockSelector::uniform(&mut rng, &["wood1", "wood2"]);
+
+    // Voxels are relative to the ground at the yard's minimum corner
+    let origin = IVec3::new(
+        min.x,
+        min.y,
+        scene.terrain.height_at(min.x, min.y).unwrap_or(0) + 1,
+    );
+    {
+        let mut painter = Painter::new(&mut model);
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let on_edge = x == min.x || x == max.x || y == min.y || y == max.y;
+                let p = IVec2::new(x, y);
+                let along_house = if back.x != 0 {
+                    x == if back.x > 0 { min.x } else { max.x }
+                } else {
+                    y == if back.y > 0 { min.y } else { max.y }
+                };
+                if !on_edge || along_house {
+                    continue;
+                }
+
+                // Posts at the corners and every third voxel, joined by a rail
+                let z = scene.terrain.height_at(x, y).unwrap_or(0) + 1;
+                let base = (p - min).extend(z - origin.z);
+                let corner = (x == min.x || x == max.x) && (y == min.y || y == max.y);
+                let post = corner || (x + y).rem_euclid(3) == 0;
+                let bottom = if post { 0 } else { 1 };
+                painter.cuboid(base + IVec3::Z * bottom, base + IVec3::Z * 2, &mut wood);
+            }
+        }
+    }
+
+    let mut object = ctx.with_center(origin).to_object(model);
+    if !scene.place_object(&mut object, &PlacementOptions::default()) {
+        return None;
+    }
+    Some(object)
+}
@@ -0,0 +1,256 @@
+//----------------------------------------------------------------------------//
+//! Compact summaries of generator output for golden regression tests.
+//!
+//! The tests below run every built-in generator over a few seeds and compare
+//! the summaries against `fixtures/golden_generators.yaml`, so any change to
+//! what a seed produces shows up as a test failure.  When a change is
+//! intentional, re-bless the fixture:
+//!
+//! ```text
+//! SNOWFALL_BLESS=1 cargo test -p snowfall_voxel golden
+//! ```
+//!
+//! Generators that load files read them from `fixtures/golden_assets`, small
+//! stand-ins for the viewer's assets (which are stored in Git LFS), so the
+//! results don't depend on the working directory or an LFS checkout.
+//!
+//----------------------------------------------------------------------------//
+
+use crate::gen_cache::fnv1a_64;
+use crate::internal::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputSummary {
+    /// Non-empty voxels across the terrain and all objects
+    pub voxels: usize,
+    /// Objects with a non-empty implementation, counting nested objects
+    pub objects: usize,
+    /// FNV-1a hash of the canonical encoding of the scene
+    pub hash: String,
+}
+
+impl OutputSummary {
+    /// Summarizes the terrain and object tree of the scene.  Object and voxel
+    /// order do not affect the result.
+    pub fn from_scene(scene: &Scene2) -> Self {
+        let mut summary = Self {
+            voxels: 0,
+            objects: 0,
+            hash: String::new(),
+        };
+        let mut bytes = Vec::new();
+        summary.write_voxel_set(&scene.terrain, &mut bytes);
+        summary.write_object(&scene.root, &mut bytes);
+        summary.hash = format!("{:016x}", fnv1a_64(&bytes));
+        summary
+    }
+
+    fn write_object(&mut self, object: &Object, out: &mut Vec<u8>) {
+        out.extend(object.generator_id.as_bytes());
+        out.push(0);
+        out.extend(object.seed.to_le_bytes());
+        write_ivec3(object.position, out);
+        out.extend(serde_json::to_vec(&object.orientation).unwrap());
+
+        match &object.imp {
+            ObjectImp::VoxelSet(voxel_set) => {
+                self.objects += 1;
+                out.push(b'V');
+                self.write_voxel_set(voxel_set, out);
+            }
+            ObjectImp::Group(group) => {
+                out.push(b'G');
+                // Encode children separately and sort so that the order
+                // objects were pushed in doesn't matter
+                let mut children: Vec<Vec<u8>> = group
+                    .objects
+                    .iter()
+                    .map(|child| {
+                        let mut bytes = Vec::new();
+                        self.write_object(child, &mut bytes);
+                        bytes
+                    })
+                    .collect();
+                children.sort();
+                for child in children {
+                    out.extend(child);
+                }
+            }
+            imp => {
+                out.extend(imp.type_str().as_bytes());
+            }
+        }
+    }
+
+    fn write_voxel_set(&mut self, voxel_set: &VoxelSet, out: &mut Vec<u8>) {
+        let mut voxels = voxel_set.voxel_iter(false);
+        voxels.sort_by_key(|(p, _)| (p.x, p.y, p.z));
+        self.voxels += voxels.len();
+
+        out.extend((voxels.len() as u64).to_le_bytes());
+        for (p, block) in voxels {
+            write_ivec3(p, out);
+            out.extend(block.id.as_bytes());
+            out.push(0);
+            if let BlockShader::RGB(rgb) = &block.shader {
+                out.extend([rgb.r, rgb.g, rgb.b]);
+            }
+            out.push(block.occupied as u8);
+            out.extend(block.walk_cost.to_bits().to_le_bytes());
+        }
+        for (name, p) in voxel_set.metadata.anchors.iter() {
+            out.extend(name.as_bytes());
+            out.push(0);
+            write_ivec3(*p, out);
+        }
+    }
+}
+
+fn write_ivec3(p: IVec3, out: &mut Vec<u8>) {
+    for v in [p.x, p.y, p.z] {
+        out.extend(v.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    type GoldenFile = BTreeMap<String, BTreeMap<u64, OutputSummary>>;
+
+    const SEEDS: [u64; 2] = [1, 2];
+
+    /// Generators that take the longest to run.  These run in
+    /// `test_golden_slow` so that the two tests can run in parallel.
+    const SLOW: [&str; 12] = [
+        "biome_hills",
+        "chest_and_key",
+        "chest_cluster",
+        "cloud",
+        "cloud_cluster",
+        "desolate_hill",
+        "flat_ground",
+        "hill2",
+        "hill3",
+        "hill4",
+        "hill_with_road",
+        "tree_hill",
+    ];
+
+    fn fixture_path(name: &str) -> std::path::PathBuf {
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures")
+            .join(name)
+    }
+
+    fn golden_context(name: &str, seed: u64) -> GenContext {
+        let ctx = GenContext::new(name, seed).with_asset_root(fixture_path("golden_assets"));
+        match name {
+            "schematic" => {
+                ctx.with_params(serde_json::json!({ "path": "signpost.schematic.yaml" }))
+            }
+            _ => ctx,
+        }
+    }
+
+    fn check_golden(generators: &[String]) {
+        // The fast and slow tests share the file, so don't let them bless
+        // concurrently
+        static FILE_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+        let _guard = FILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let bless = std::env::var("SNOWFALL_BLESS").is_ok_and(|v| v == "1");
+        let path = fixture_path("golden_generators.yaml");
+        let mut golden: GoldenFile = match std::fs::read_to_string(&path) {
+            Ok(s) => serde_yaml::from_str(&s).unwrap(),
+            Err(_) => GoldenFile::new(),
+        };
+
+        let mut failures = Vec::new();
+        for name in generators {
+            for seed in SEEDS {
+                let scene = Scene2::generate(&golden_context(name, seed));
+                let summary = OutputSummary::from_scene(&scene);
+                let expected = golden.get(name).and_then(|seeds| seeds.get(&seed));
+                if expected != Some(&summary) {
+                    failures.push(format!(
+                        "{} seed {}: expected {:?}, got {:?}",
+                        name, seed, expected, summary
+                    ));
+                    golden
+                        .entry(name.clone())
+                        .or_default()
+                        .insert(seed, summary);
+                }
+            }
+        }
+
+        if bless {
+            if !failures.is_empty() {
+                std::fs::write(&path, serde_yaml::to_string(&golden).unwrap()).unwrap();
+            }
+            return;
+        }
+        assert!(
+            failures.is_empty(),
+            "generator output changed (re-bless with SNOWFALL_BLESS=1 if intended):\n{}",
+            failures.join("\n")
+        );
+    }
+
+    #[test]
+    fn test_golden_lists_are_current() {
+        // Every other built-in generator is covered by test_golden_fast, so
+        // only stale names need checking here
+        for name in SLOW.iter() {
+            assert!(
+                GeneratorRegistry::builtin().get(name).is_some(),
+                "{} is not a registered generator",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_golden_fast() {
+        let generators: Vec<String> = GeneratorRegistry::builtin()
+            .iter()
+            .map(|g| g.name().to_string())
+            .filter(|name| !SLOW.contains(&name.as_str()))
+            .collect();
+        check_golden(&generators);
+    }
+
+    #[test]
+    fn test_golden_slow() {
+        let generators: Vec<String> = SLOW.iter().map(|name| name.to_string()).collect();
+        check_golden(&generators);
+    }
+
+    #[test]
+    fn test_summary_ignores_order() {
+        let mut model = VoxelSet::new();
+        model.register_block(Block::color("stone", 80, 80, 80));
+        model.set_voxel((0, 0, 0), "stone");
+
+        let ctx = GenContext::new("test", 1);
+        let summarize = |centers: &[IVec3]| {
+            let mut group = Group::new();
+            for center in centers {
+                group.push(&ctx.with_center(*center), model.clone());
+            }
+            let mut scene = Scene2::new();
+            scene.root.imp = ObjectImp::Group(Box::new(group));
+            OutputSummary::from_scene(&scene)
+        };
+
+        let a = IVec3::new(0, 0, 0);
+        let b = IVec3::new(4, 0, 0);
+        let summary = summarize(&[a, b]);
+        assert_eq!(summary.voxels, 2);
+        assert_eq!(summary.objects, 2);
+        assert_eq!(summary, summarize(&[b, a]));
+        assert_ne!(summary, summarize(&[a, IVec3::new(5, 0, 0)]));
+    }
+}
@@ -0,0 +1,294 @@
+//----------------------------------------------------------------------------//
+//! Adapting the scene terrain to placed objects.
+//!
+//! Objects are usually dropped at `height_at + 1` of their center, which
+//! leaves them floating or buried on uneven ground.  After an object is in
+//! its final position, `Scene2::fit_ground` either reshapes the terrain
+//! under it to meet its base (cut, fill, or both) or builds a foundation
+//! down from its base to the ground.
+//!
+//! Reshaped areas are blended back into the surrounding terrain over a band
+//! of columns, so leveled areas slope into hills rather than ending in a
+//! cliff.
+//!
+//----------------------------------------------------------------------------//
+
+use crate::internal::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroundMode {
+    /// Cut and fill so the ground is level
+    #[default]
+    Flatten,
+    /// Only lower the ground
+    Cut,
+    /// Only raise the ground
+    Fill,
+    /// Leave the ground as is and extend the base of the object down to it
+    Foundation,
+}
+
+impl GroundMode {
+    pub const NAMES: [&'static str; 4] = ["flatten", "cut", "fill", "foundation"];
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GroundOptions {
+    pub mode: GroundMode,
+
+    /// Extra X/Y columns around the footprint that are leveled as well
+    pub margin: i32,
+
+    /// Width of the band of columns sloping from the leveled area back to
+    /// the original terrain
+    pub blend: i32,
+}
+
+impl Default for GroundOptions {
+    fn default() -> Self {
+        Self {
+            mode: GroundMode::Flatten,
+            margin: 1,
+            blend: 4,
+        }
+    }
+}
+
+impl GroundOptions {
+    pub fn with_mode(mut self, mode: GroundMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_margin(mut self, margin: i32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    pub fn with_blend(mut self, blend: i32) -> Self {
+        self.blend = blend;
+        self
+    }
+}
+
+impl Scene2 {
+    /// Adapts the terrain to the base of the object, which should already be
+    /// at its final position.  Group members are included.
+    pub fn fit_ground(&mut self, object: &Object, options: &GroundOptions) {
+        let footprint = object.footprint();
+        if footprint.is_empty() {
+            return;
+        }
+
+        if options.mode == GroundMode::Foundation {
+            let mut columns = HashMap::new();
+            base_columns(object, &mut columns);
+            self.build_foundation(&columns);
+            return;
+        }
+
+        let mut bounds = footprint[0];
+        for b in &footprint[1..] {
+            bounds.add(b.min);
+            bounds.add(b.max);
+        }
+        self.level_ground(
+            bounds.min.truncate(),
+            bounds.max.truncate(),
+            bounds.min.z - 1,
+            options,
+        );
+    }
+
+    /// Moves the top terrain voxel of each column from `min` to `max`
+    /// (inclusive) to `level`, blending the surrounding columns back to the
+    /// original terrain.  Columns without terrain are left empty.
+    pub fn level_ground(&mut self, min: IVec2, max: IVec2, level: i32, options: &GroundOptions) {
+        if options.mode == GroundMode::Foundation {
+            return;
+        }
+
+        let min = min - IVec2::splat(options.margin);
+        let max = max + IVec2::splat(options.margin);
+        let blend = options.blend.max(0);
+
+        for y in (min.y - blend)..=(max.y + blend) {
+            for x in (min.x - blend)..=(max.x + blend) {
+                let Some(height) = self.terrain.height_at(x, y) else {
+                    continue;
+                };
+
+                // Distance outside the leveled rectangle, 0 within it
+                let dx = (min.x - x).max(x - max.x).max(0);
+                let dy = (min.y - y).max(y - max.y).max(0);
+                let d = ((dx * dx + dy * dy) as f32).sqrt();
+                if d > blend as f32 {
+                    continue;
+                }
+
+                let t = d / (blend + 1) as f32;
+                let t = t * t * (3.0 - 2.0 * t);
+                let target = (level as f32 + (height - level) as f32 * t).round() as i32;
+                let target = match options.mode {
+                    GroundMode::Cut => target.min(height),
+                    GroundMode::Fill => target.max(height),
+                    _ => target,
+                };
+                set_column_height(&mut self.terrain, x, y, height, target);
+            }
+        }
+    }
+
+    /// Fills each column from the ground up to (but not including) the given
+    /// base height with the given block.
+    fn build_foundation(&mut self, columns: &HashMap<(i32, i32), (i32, Block)>) {
+        for (&(x, y), (base, block)) in columns {
+            let Some(ground) = self.terrain.height_at(x, y) else {
+                continue;
+            };
+            if ground + 1 >= *base {
+                continue;
+            }
+            let index = self.terrain.ensure_block(block.clone());
+            for z in (ground + 1)..*base {
+                self.terrain.set((x, y, z), index);
+            }
+        }
+    }
+}
+
+/// Collects the lowest voxel of each world-space column of the object.
+fn base_columns(object: &Object, columns: &mut HashMap<(i32, i32), (i32, Block)>) {
+    match &object.imp {
+        ObjectImp::VoxelSet(_) => {
+            let voxel_set = object.oriented_voxel_set().unwrap();
+            for (p, block) in voxel_set.voxel_iter(false) {
+                let p = p + object.position;
+                let lower = columns.get(&(p.x, p.y)).is_none_or(|(z, _)| p.z < *z);
+                if lower {
+                    columns.insert((p.x, p.y), (p.z, block.clone()));
+                }
+            }
+        }
+        ObjectImp::Group(group) => {
+            for child in group.objects.iter() {
+                base_columns(child, columns);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Moves the top voxel of the column from `height` to `target`.  Cut
+/// columns keep their top block; filled columns are filled with the block
+/// that was under the top.
+fn set_column_height(terrain: &mut VoxelSet, x: i32, y: i32, height: i32, target: i32) {
+    if target == height {
+        return;
+    }
+
+    let top = terrain.ensure_block(terrain.get_voxel((x, y, height)).clone());
+    if target < height {
+        for z in (target + 1)..=height {
+            terrain.clear_voxel((x, y, z));
+        }
+    } else {
+        let below = terrain.get_voxel((x, y, height - 1));
+        let fill = if below.is_empty() {
+            top
+        } else {
+            terrain.ensure_block(below.clone())
+        };
+        for z in height..target {
+            terrain.set((x, y, z), fill);
+        }
+    }
+    terrain.set((x, y, target), top);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Terrain sloping up along X, three voxels deep.
+    fn slope_scene() -> Scene2 {
+        let mut scene = Scene2::new();
+        scene
+            .terrain
+            .register_block(Block::color("grass", 5, 60, 10));
+        scene.terrain.register_block(Block::color("dirt", 10, 8, 4));
+        for y in -20..=20 {
+            for x in -20..=20 {
+                let h = x / 2;
+                scene.terrain.fill_box((x, y, h - 2), (x, y, h - 1), "dirt");
+                scene.terrain.set_voxel((x, y, h), "grass");
+            }
+        }
+        scene
+    }
+
+    fn stone_object(ctx: &GenContext) -> Object {
+        let mut model = VoxelSet::new();
+        model.register_block(Block::color("stone", 80, 80, 80));
+        model.fill_box((-2, -2, 0), (2, 2, 1), "stone");
+        ctx.to_object(model)
+    }
+
+    #[test]
+    fn test_flatten_and_blend() {
+        let mut scene = slope_scene();
+        let object = stone_object(&GenContext::new("stone", 1).with_center(IVec3::new(0, 0, 1)));
+        let options = GroundOptions::default().with_margin(0).with_blend(4);
+        scene.fit_ground(&object, &options);
+
+        for x in -2..=2 {
+            assert_eq!(scene.terrain.height_at(x, 0), Some(0));
+            assert_eq!(scene.terrain.top_block_at(x, 0).unwrap().id, "grass");
+        }
+        // Filled columns are filled with what was under the top
+        assert_eq!(scene.terrain.get_voxel((-2, 0, -1)).id, "dirt");
+
+        // The blend band rises monotonically from the level to the slope
+        let heights: Vec<i32> = (2..=8)
+            .map(|x| scene.terrain.height_at(x, 0).unwrap())
+            .collect();
+        assert!(heights.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(*heights.last().unwrap(), 4);
+        assert_eq!(scene.terrain.height_at(12, 0), Some(6));
+    }
+
+    #[test]
+    fn test_cut_and_fill_only() {
+        let ctx = GenContext::new("stone", 1).with_center(IVec3::new(0, 0, 1));
+
+        let mut scene = slope_scene();
+        let options = GroundOptions::default().with_mode(GroundMode::Cut);
+        scene.fit_ground(&stone_object(&ctx), &options);
+        assert_eq!(scene.terrain.height_at(2, 0), Some(0));
+        assert_eq!(scene.terrain.height_at(-2, 0), Some(-1));
+
+        let mut scene = slope_scene();
+        let options = GroundOptions::default().with_mode(GroundMode::Fill);
+        scene.fit_ground(&stone_object(&ctx), &options);
+        assert_eq!(scene.terrain.height_at(2, 0), Some(1));
+        assert_eq!(scene.terrain.height_at(-2, 0), Some(0));
+    }
+
+    #[test]
+    fn test_foundation() {
+        let mut scene = slope_scene();
+        let ctx = GenContext::new("stone", 1).with_center(IVec3::new(0, 0, 3));
+        let options = GroundOptions::default().with_mode(GroundMode::Foundation);
+        scene.fit_ground(&stone_object(&ctx), &options);
+
+        // The terrain surface is unchanged outside the object, and every
+        // column under it now reaches the object's base
+        assert_eq!(scene.terrain.height_at(3, 0), Some(1));
+        for x in -2..=2 {
+            assert_eq!(scene.terrain.height_at(x, 0), Some(2));
+            assert_eq!(scene.terrain.get_voxel((x, 0, 2)).id, "stone");
+        }
+        assert_eq!(scene.terrain.get_voxel((-2, 0, -1)).id, "grass");
+    }
+}
@@ -0,0 +1,253 @@
+//----------------------------------------------------------------------------//
+//! Import and export of terrain as grayscale heightmap images.
+//!
+//! Pixel values are normalized to [0, 1] (8-bit images are expanded to
+//! 16-bit) and multiplied by the vertical scale to get the column height.
+//! The image is centered on the origin with image "up" along +Y.
+//!
+//----------------------------------------------------------------------------//
+
+use crate::internal::*;
+use image::{ImageBuffer, Luma};
+use std::path::Path;
+
+pub type HeightmapImage = ImageBuffer<Luma<u16>, Vec<u16>>;
+
+/// A band of blocks measured down from the surface of a column.
+#[derive(Debug, Clone)]
+pub struct HeightmapLayer {
+    pub block: Block,
+    /// Thickness of the band.  The last layer of a rule always extends to the
+    /// bottom of the column.
+    pub depth: i32,
+}
+
+/// The layers to use for columns whose height is at least `min_height`,
+/// e.g. to put snow on peaks and sand in valleys.
+#[derive(Debug, Clone)]
+pub struct HeightmapRule {
+    pub min_height: i32,
+    pub layers: Vec<HeightmapLayer>,
+}
+
+pub struct HeightmapOptions {
+    /// Height in voxels of a full intensity pixel
+    pub vertical_scale: f32,
+    /// Voxels are filled from `base_z + 1` up to the column height above it
+    pub base_z: i32,
+    pub rules: Vec<HeightmapRule>,
+}
+
+impl Default for HeightmapOptions {
+    fn default() -> Self {
+        Self {
+            vertical_scale: 64.0,
+            base_z: 0,
+            rules: vec![HeightmapRule {
+                min_height: i32::MIN,
+                layers: vec![
+                    HeightmapLayer {
+                        block: Block::color("grass", 5, 60, 10),
+                        depth: 1,
+                    },
+                    HeightmapLayer {
+                        block: Block::color("dirt", 16, 12, 7),
+                        depth: 3,
+                    },
+                    HeightmapLayer {
+                        block: Block::color("stone", 40, 40, 40),
+                        depth: 1,
+                    },
+                ],
+            }],
+        }
+    }
+}
+
+impl HeightmapOptions {
+    pub fn with_vertical_scale(mut self, vertical_scale: f32) -> Self {
+        self.vertical_scale = vertical_scale;
+        self
+    }
+
+    pub fn with_base_z(mut self, base_z: i32) -> Self {
+        self.base_z = base_z;
+        self
+    }
+
+    /// Replaces the default rules.  Rules may be given in any order.
+    pub fn with_rules(mut self, rules: Vec<HeightmapRule>) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    fn height(&self, value: u16) -> i32 {
+        (value as f32 / u16::MAX as f32 * self.vertical_scale).round() as i32
+    }
+
+    fn value(&self, height: i32) -> u16 {
+        let v = height as f32 / self.vertical_scale * u16::MAX as f32;
+        v.round().clamp(0.0, u16::MAX as f32) as u16
+    }
+
+    fn rule_for(&self, height: i32) -> Option<&HeightmapRule> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.min_height <= height)
+            .max_by_key(|rule| rule.min_height)
+    }
+
+    /// Calls `f` with the position and block of every voxel in the terrain.
+    fn for_each_voxel<F>(&self, img: &HeightmapImage, mut f: F)
+    where
+        F: FnMut(IVec3, &Block),
+    {
+        let (width, height) = img.dimensions();
+        let (cx, cy) = ((width / 2) as i32, (height / 2) as i32);
+        for (px, py, pixel) in img.enumerate_pixels() {
+            let h = self.height(pixel.0[0]);
+            let Some(rule) = self.rule_for(h) else {
+                continue;
+            };
+            let x = px as i32 - cx;
+            let y = (height - py - 1) as i32 - cy;
+
+            let mut layer = 0;
+            let mut layer_bottom = h - rule.layers.first().map_or(0, |l| l.depth);
+            for z in (1..=h).rev() {
+                while z <= layer_bottom && layer + 1 < rule.layers.len() {
+                    layer += 1;
+                    layer_bottom -= rule.layers[layer].depth;
+                }
+                if let Some(l) = rule.layers.get(layer) {
+                    f(IVec3::new(x, y, self.base_z + z), &l.block);
+                }
+            }
+        }
+    }
+}
+
+pub fn read_heightmap_file<P: AsRef<Path>>(path: P) -> Result<HeightmapImage, Error> {
+    let path = path.as_ref();
+    let img = image::open(path).map_err(|e| Error::Image(format!("{}: {}", path.display(), e)))?;
+    Ok(img.into_luma16())
+}
+
+/// Builds a 16-bit heightmap covering the inclusive XY range from `min` to
+/// `max` using any height function, e.g. `VoxelSet::height_at`.  Columns
+/// without a height are written as zero.
+pub fn build_heightmap<F>(
+    min: IVec2,
+    max: IVec2,
+    options: &HeightmapOptions,
+    height_at: F,
+) -> HeightmapImage
+where
+    F: Fn(i32, i32) -> Option<i32>,
+{
+    let width = (max.x - min.x + 1).max(0) as u32;
+    let height = (max.y - min.y + 1).max(0) as u32;
+    HeightmapImage::from_fn(width, height, |px, py| {
+        let x = min.x + px as i32;
+        let y = max.y - py as i32;
+        let h = height_at(x, y).map_or(0, |z| z - options.base_z);
+        Luma([options.value(h)])
+    })
+}
+
+impl VoxelSet {
+    pub fn from_heightmap(img: &HeightmapImage, options: &HeightmapOptions) -> VoxelSet {
+        let mut model = VoxelSet::new();
+        let mut indices: HashMap<String, PaletteIndex> = HashMap::new();
+        options.for_each_voxel(img, |p, block| {
+            let index = *indices
+                .entry(block.id.clone())
+                .or_insert_with(|| model.ensure_block(block.clone()));
+            model.set(p, index);
+        });
+        model
+    }
+
+    pub fn read_heightmap_file<P: AsRef<Path>>(
+        path: P,
+        options: &HeightmapOptions,
+    ) -> Result<VoxelSet, Error> {
+        let img = read_heightmap_file(path)?;
+        Ok(VoxelSet::from_heightmap(&img, options))
+    }
+
+    /// Exports `height_at` over the XY bounds of the set.
+    pub fn to_heightmap(&self, options: &HeightmapOptions) -> HeightmapImage {
+        let bounds = self.bounds();
+        if bounds.min.x > bounds.max.x {
+            return HeightmapImage::new(0, 0);
+        }
+        build_heightmap(
+            bounds.min.truncate(),
+            bounds.max.truncate(),
+            options,
+            |x, y| self.height_at(x, y),
+        )
+    }
+
+    pub fn write_heightmap_file(
+        &self,
+        path: &str,
+        options: &HeightmapOptions,
+    ) -> Result<(), Error> {
+        self.to_heightmap(options)
+            .save(path)
+            .map_err(|e| Error::Image(format!("{}: {}", path, e)))
+    }
+}
+
+impl VoxelGrid {
+    pub fn from_heightmap(img: &HeightmapImage, options: &HeightmapOptions) -> VoxelGrid {
+        let mut grid = VoxelGrid::new();
+        grid.stamp_heightmap(img, options);
+        grid
+    }
+
+    /// Writes the heightmap terrain into an existing grid.
+    pub fn stamp_heightmap(&mut self, img: &HeightmapImage, options: &HeightmapOptions) {
+        let mut indices: HashMap<String, usize> = HashMap::new();
+        options.for_each_voxel(img, |p, block| {
+            let index = *indices
+                .entry(block.id.clone())
+                .or_insert_with(|| self.ensure_block(block));
+            self.set_index(p, index);
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_heightmap_layers() {
+        let img = HeightmapImage::from_fn(3, 2, |x, _| Luma([x as u16 * 257 * 3]));
+        let options = HeightmapOptions::default().with_vertical_scale(255.0);
+        let model = VoxelSet::from_heightmap(&img, &options);
+
+        assert_eq!(model.height_at(-1, 0), None);
+        assert_eq!(model.height_at(0, 0), Some(3));
+        assert_eq!(model.height_at(1, -1), Some(6));
+        assert_eq!(model.get_voxel((1, 0, 6)).id, "grass");
+        assert_eq!(model.get_voxel((1, 0, 3)).id, "dirt");
+        assert_eq!(model.get_voxel((1, 0, 2)).id, "stone");
+        assert_eq!(model.get_voxel((1, 0, 1)).id, "stone");
+    }
+
+    #[test]
+    fn test_heightmap_round_trip() {
+        let img = HeightmapImage::from_fn(4, 3, |x, y| Luma([(1 + x + 4 * y) as u16 * 257]));
+        let options = HeightmapOptions::default()
+            .with_vertical_scale(255.0)
+            .with_base_z(10);
+        let model = VoxelSet::from_heightmap(&img, &options);
+        let exported = model.to_heightmap(&options);
+        assert_eq!(exported.dimensions(), img.dimensions());
+        assert_eq!(exported.as_raw(), img.as_raw());
+    }
+}
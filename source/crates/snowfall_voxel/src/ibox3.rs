@@ -2,6 +2,7 @@ use crate::internal::*;
 
 /// Integer based axis-aligned bounding box. The min and max are inclusive.
 ///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct IBox3 {
     pub min: IVec3,
     pub max: IVec3,
@@ -20,10 +21,26 @@ impl IBox3 {
         }
     }
 
+    pub fn from_min_max(min: IVec3, max: IVec3) -> Self {
+        Self { min, max }
+    }
+
     // ---------------------------------------------------------------------
     // Properties
     // ---------------------------------------------------------------------
 
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn contains(&self, p: IVec3) -> bool {
+        p.cmpge(self.min).all() && p.cmple(self.max).all()
+    }
+
+    pub fn intersects(&self, other: &IBox3) -> bool {
+        self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+
     pub fn volume(&self) -> i32 {
         self.length_x() * self.length_y() * self.length_z()
     }
@@ -1,9 +1,30 @@
+mod biome;
 mod block;
+mod csg;
+mod gen_cache;
+mod generator;
 mod generators;
+mod golden;
+mod ground;
+mod heightmap;
 mod ibox3;
+mod mesh_export;
+mod migrate;
+mod noise_terrain;
 mod paint;
+mod painter;
+mod param_schema;
+mod placement;
 mod point_set;
+mod recipe;
+mod region_file;
+mod render;
 mod scene2;
+mod schematic;
+mod sprite;
+mod stamp;
+mod transform;
+mod vox;
 mod voxel_grid;
 mod voxel_model;
 mod voxel_palette;
@@ -12,15 +33,34 @@ mod voxel_set;
 
 pub mod prelude {
     // TODO: tidy up the wildcard exports once this crate stabilizes a bit
+    pub use crate::biome::*;
     pub use crate::block::*;
+    pub use crate::gen_cache::*;
+    pub use crate::generator::*;
+    pub use crate::golden::*;
+    pub use crate::ground::*;
     pub mod generators {
         pub use crate::generators::*;
     }
-    pub use super::generators::generate_model;
+    pub use super::generators::{generate_model, try_generate_model};
+    pub use crate::heightmap::*;
     pub use crate::ibox3::*;
+    pub use crate::mesh_export::*;
+    pub use crate::migrate::*;
+    pub use crate::noise_terrain::*;
     pub use crate::paint::{GenContext, Model};
+    pub use crate::painter::*;
+    pub use crate::param_schema::*;
+    pub use crate::placement::*;
     pub use crate::point_set::*;
+    pub use crate::recipe::*;
+    pub use crate::region_file::*;
+    pub use crate::render::*;
     pub use crate::scene2::*;
+    pub use crate::schematic::*;
+    pub use crate::sprite::*;
+    pub use crate::stamp::*;
+    pub use crate::transform::*;
     pub use crate::voxel_grid::{VoxelGrid, VoxelGridGenerator, VoxelGridPager};
     pub use crate::voxel_model::*;
     pub use crate::voxel_palette::*;
@@ -0,0 +1,394 @@
+//----------------------------------------------------------------------------//
+//! Export of voxel meshes to glTF 2.0 binary (.glb) and Wavefront OBJ.
+//!
+//! The meshes are the same triangle lists produced by `build_mesh_arrays` for
+//! the runtime renderer, so exported models match what the viewer shows.
+//!
+//----------------------------------------------------------------------------//
+
+use crate::internal::*;
+use std::fmt::Write;
+
+/// A mesh plus its placement in an exported scene.
+///
+/// The placement mirrors how the voxel viewer positions models: the mesh is
+/// scaled, then rotated about Z, then translated.
+pub struct MeshInstance {
+    pub name: String,
+    pub mesh: VoxelMesh,
+    pub translation: Vec3,
+    pub rotation_z: f32,
+    pub scale: f32,
+}
+
+impl MeshInstance {
+    /// Builds the mesh for a VoxelSet, applying its `Scale` and `RotateZ`
+    /// attributes.  Note `RotateZ` is applied as a static rotation since an
+    /// exported file has no notion of the viewer's animation.
+    pub fn from_voxel_set<T>(name: T, voxel_set: &VoxelSet, translation: Vec3) -> Self
+    where
+        T: Into<String>,
+    {
+        let mut scale = 1.0;
+        let mut rotation_z = 0.0;
+        for attribute in voxel_set.attributes.iter() {
+            match attribute {
+                VoxelSetAttribute::Scale(s) => scale = *s,
+                VoxelSetAttribute::RotateZ(r) => rotation_z = *r,
+                _ => {}
+            }
+        }
+        Self {
+            name: name.into(),
+            mesh: build_mesh_arrays(voxel_set),
+            translation,
+            rotation_z,
+            scale,
+        }
+    }
+
+    pub(crate) fn transform_point(&self, p: [f32; 3]) -> [f32; 3] {
+        let v = Vec3::from(p) * self.scale;
+        let v = Quat::from_rotation_z(self.rotation_z) * v + self.translation;
+        v.into()
+    }
+
+    pub(crate) fn transform_normal(&self, n: [f32; 3]) -> [f32; 3] {
+        (Quat::from_rotation_z(self.rotation_z) * Vec3::from(n)).into()
+    }
+}
+
+/// Collects a mesh for the terrain and every VoxelSet object in the scene,
+/// including objects nested in groups.
+pub fn scene_mesh_instances(scene: &Scene2) -> Vec<MeshInstance> {
+    fn collect(object: &Object, instances: &mut Vec<MeshInstance>) {
+        match &object.imp {
+            ObjectImp::VoxelSet(_) => {
+                let voxel_set = object.oriented_voxel_set().unwrap();
+                let name = format!(
+                    "{}-{}-{}",
+                    object.generator_id,
+                    object.seed,
+                    instances.len()
+                );
+                let p = object.position;
+                let translation = Vec3::new(p.x as f32, p.y as f32, p.z as f32);
+                instances.push(MeshInstance::from_voxel_set(name, &voxel_set, translation));
+            }
+            ObjectImp::Group(group) => {
+                for child in group.objects.iter() {
+                    collect(child, instances);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut instances = vec![MeshInstance::from_voxel_set(
+        "terrain",
+        &scene.terrain,
+        Vec3::ZERO,
+    )];
+    collect(&scene.root, &mut instances);
+    instances
+}
+
+// ----------------------------------------------------------------------------
+// Convenience methods
+// ----------------------------------------------------------------------------
+
+impl VoxelSet {
+    pub fn to_glb_bytes(&self) -> Vec<u8> {
+        build_glb(&[MeshInstance::from_voxel_set("model", self, Vec3::ZERO)])
+    }
+
+    pub fn to_obj_string(&self) -> String {
+        build_obj(&[MeshInstance::from_voxel_set("model", self, Vec3::ZERO)])
+    }
+
+    pub fn write_glb_file(&self, path: &str) -> Result<(), Error> {
+        std::fs::write(path, self.to_glb_bytes())?;
+        Ok(())
+    }
+
+    pub fn write_obj_file(&self, path: &str) -> Result<(), Error> {
+        std::fs::write(path, self.to_obj_string())?;
+        Ok(())
+    }
+}
+
+impl Scene2 {
+    pub fn to_glb_bytes(&self) -> Vec<u8> {
+        build_glb(&scene_mesh_instances(self))
+    }
+
+    pub fn to_obj_string(&self) -> String {
+        build_obj(&scene_mesh_instances(self))
+    }
+
+    pub fn write_glb_file(&self, path: &str) -> Result<(), Error> {
+        std::fs::write(path, self.to_glb_bytes())?;
+        Ok(())
+    }
+
+    pub fn write_obj_file(&self, path: &str) -> Result<(), Error> {
+        std::fs::write(path, self.to_obj_string())?;
+        Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Wavefront OBJ
+// ----------------------------------------------------------------------------
+
+/// Writes the instances as a single OBJ file with one object per instance.
+/// The transforms are baked into the vertices since OBJ has no scene graph.
+/// Vertex colors use the common `v x y z r g b` extension.
+pub fn build_obj(instances: &[MeshInstance]) -> String {
+    let mut out = String::new();
+    writeln!(out, "# Exported by snowfall_voxel").unwrap();
+
+    let mut base = 1;
+    for instance in instances {
+        let mesh = &instance.mesh;
+        if mesh.positions.is_empty() {
+            continue;
+        }
+        writeln!(out, "o {}", instance.name).unwrap();
+        for (p, c) in mesh.positions.iter().zip(mesh.colors.iter()) {
+            let [x, y, z] = instance.transform_point(*p);
+            writeln!(out, "v {} {} {} {} {} {}", x, y, z, c[0], c[1], c[2]).unwrap();
+        }
+        for n in mesh.normals.iter() {
+            let [x, y, z] = instance.transform_normal(*n);
+            writeln!(out, "vn {} {} {}", x, y, z).unwrap();
+        }
+        for i in (0..mesh.positions.len()).step_by(3) {
+            let (a, b, c) = (base + i, base + i + 1, base + i + 2);
+            writeln!(out, "f {}//{} {}//{} {}//{}", a, a, b, b, c, c).unwrap();
+        }
+        base += mesh.positions.len();
+    }
+    out
+}
+
+// ----------------------------------------------------------------------------
+// glTF 2.0 binary
+// ----------------------------------------------------------------------------
+
+const GLB_MAGIC: u32 = 0x4654_6C67; // "glTF"
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN: u32 = 0x004E_4942;
+const GLTF_FLOAT: u32 = 5126;
+const GLTF_ARRAY_BUFFER: u32 = 34962;
+const GLTF_TRIANGLES: u32 = 4;
+
+/// Writes the instances as a .glb file with one node and mesh per instance.
+/// Instances with no geometry are skipped.
+///
+/// glTF is +Y up, so the instance nodes are parented to a root node that
+/// rotates snowfall's +Z up coordinates into place.
+pub fn build_glb(instances: &[MeshInstance]) -> Vec<u8> {
+    use serde_json::json;
+
+    let mut bin: Vec<u8> = Vec::new();
+    let mut buffer_views = Vec::new();
+    let mut accessors = Vec::new();
+    let mut meshes = Vec::new();
+    let mut nodes = Vec::new();
+
+    let mut push_view = |bin: &mut Vec<u8>, data: &[f32]| -> usize {
+        let offset = bin.len();
+        for v in data {
+            bin.extend_from_slice(&v.to_le_bytes());
+        }
+        buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": data.len() * 4,
+            "target": GLTF_ARRAY_BUFFER,
+        }));
+        buffer_views.len() - 1
+    };
+
+    for instance in instances {
+        let mesh = &instance.mesh;
+        if mesh.positions.is_empty() {
+            continue;
+        }
+        let count = mesh.positions.len();
+
+        let mut min = Vec3::splat(f32::MAX);
+        let mut max = Vec3::splat(f32::MIN);
+        for p in mesh.positions.iter() {
+            min = min.min(Vec3::from(*p));
+            max = max.max(Vec3::from(*p));
+        }
+
+        let positions: Vec<f32> = mesh.positions.iter().flatten().copied().collect();
+        let normals: Vec<f32> = mesh.normals.iter().flatten().copied().collect();
+        let colors: Vec<f32> = mesh.colors.iter().flatten().copied().collect();
+        let positions = push_view(&mut bin, &positions);
+        let normals = push_view(&mut bin, &normals);
+        let colors = push_view(&mut bin, &colors);
+
+        let first = accessors.len();
+        accessors.push(json!({
+            "bufferView": positions,
+            "componentType": GLTF_FLOAT,
+            "count": count,
+            "type": "VEC3",
+            "min": [min.x, min.y, min.z],
+            "max": [max.x, max.y, max.z],
+        }));
+        accessors.push(json!({
+            "bufferView": normals,
+            "componentType": GLTF_FLOAT,
+            "count": count,
+            "type": "VEC3",
+        }));
+        accessors.push(json!({
+            "bufferView": colors,
+            "componentType": GLTF_FLOAT,
+            "count": count,
+            "type": "VEC4",
+        }));
+
+        meshes.push(json!({
+            "name": instance.name,
+            "primitives": [{
+                "attributes": {
+                    "POSITION": first,
+                    "NORMAL": first + 1,
+                    "COLOR_0": first + 2,
+                },
+                "material": 0,
+                "mode": GLTF_TRIANGLES,
+            }],
+        }));
+
+        let q = Quat::from_rotation_z(instance.rotation_z);
+        let t = instance.translation;
+        nodes.push(json!({
+            "name": instance.name,
+            "mesh": meshes.len() - 1,
+            "translation": [t.x, t.y, t.z],
+            "rotation": [q.x, q.y, q.z, q.w],
+            "scale": [instance.scale, instance.scale, instance.scale],
+        }));
+    }
+
+    let q = Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2);
+    let mut root = json!({
+        "name": "root",
+        "rotation": [q.x, q.y, q.z, q.w],
+    });
+    if !nodes.is_empty() {
+        root["children"] = json!((0..nodes.len()).collect::<Vec<_>>());
+    }
+    let root_index = nodes.len();
+    nodes.push(root);
+
+    // The glTF schema requires these arrays to be non-empty when present
+    let mut document = json!({
+        "asset": { "version": "2.0", "generator": "snowfall_voxel" },
+        "scene": 0,
+        "scenes": [{ "nodes": [root_index] }],
+        "nodes": nodes,
+        "materials": [{
+            "name": "voxel",
+            "pbrMetallicRoughness": {
+                "baseColorFactor": [1.0, 1.0, 1.0, 1.0],
+                "metallicFactor": 0.0,
+                "roughnessFactor": 1.0,
+            },
+        }],
+    });
+    if !meshes.is_empty() {
+        document["meshes"] = json!(meshes);
+        document["accessors"] = json!(accessors);
+        document["bufferViews"] = json!(buffer_views);
+    }
+    if !bin.is_empty() {
+        document["buffers"] = json!([{ "byteLength": bin.len() }]);
+    }
+
+    let mut json_bytes = serde_json::to_vec(&document).unwrap();
+    while !json_bytes.len().is_multiple_of(4) {
+        json_bytes.push(b' ');
+    }
+    while !bin.len().is_multiple_of(4) {
+        bin.push(0);
+    }
+
+    let mut total = 12 + 8 + json_bytes.len();
+    if !bin.is_empty() {
+        total += 8 + bin.len();
+    }
+
+    let mut out = Vec::with_capacity(total);
+    out.extend_from_slice(&GLB_MAGIC.to_le_bytes());
+    out.extend_from_slice(&2u32.to_le_bytes());
+    out.extend_from_slice(&(total as u32).to_le_bytes());
+    out.extend_from_slice(&(json_bytes.len() as u32).to_le_bytes());
+    out.extend_from_slice(&GLB_CHUNK_JSON.to_le_bytes());
+    out.extend_from_slice(&json_bytes);
+    if !bin.is_empty() {
+        out.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        out.extend_from_slice(&GLB_CHUNK_BIN.to_le_bytes());
+        out.extend_from_slice(&bin);
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample_set() -> VoxelSet {
+        let mut model = VoxelSet::new();
+        model.register_block(Block::color("red", 255, 0, 0));
+        model.fill_box((0, 0, 0), (1, 0, 0), "red");
+        model
+    }
+
+    #[test]
+    fn test_obj_export() {
+        let obj = sample_set().to_obj_string();
+        // Two adjacent cubes expose 10 faces of 2 triangles each
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 60);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 20);
+    }
+
+    #[test]
+    fn test_glb_export() {
+        let bytes = sample_set().to_glb_bytes();
+        assert_eq!(&bytes[0..4], b"glTF");
+        let total = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
+        assert_eq!(total, bytes.len());
+
+        let json_len = u32::from_le_bytes(bytes[12..16].try_into().unwrap()) as usize;
+        let document: serde_json::Value =
+            serde_json::from_slice(&bytes[20..20 + json_len]).unwrap();
+        assert_eq!(document["meshes"].as_array().unwrap().len(), 1);
+        assert_eq!(document["accessors"][0]["count"], 60);
+        assert_eq!(document["buffers"][0]["byteLength"], 60 * 10 * 4);
+
+        let root = document["scenes"][0]["nodes"][0].as_u64().unwrap() as usize;
+        let q = &document["nodes"][root]["rotation"];
+        assert!((q[0].as_f64().unwrap() + 0.5f64.sqrt()).abs() < 1e-6);
+        assert_eq!(document["nodes"][root]["children"][0], 0);
+    }
+
+    #[test]
+    fn test_glb_export_empty() {
+        let bytes = build_glb(&[]);
+        let json_len = u32::from_le_bytes(bytes[12..16].try_into().unwrap()) as usize;
+        let document: serde_json::Value =
+            serde_json::from_slice(&bytes[20..20 + json_len]).unwrap();
+        for key in ["meshes", "accessors", "bufferViews", "buffers"] {
+            assert!(document.get(key).is_none(), "{} should be omitted", key);
+        }
+        assert!(document["nodes"][0].get("children").is_none());
+    }
+}
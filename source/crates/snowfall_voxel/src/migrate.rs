@@ -0,0 +1,208 @@
+//----------------------------------------------------------------------------//
+//! Upgrading of older file versions to the current file structures.
+//!
+//! Each file type keeps a registry of single-version steps.  Loading a file
+//! walks the steps from the file's version until it reaches the current
+//! version, so a format change only needs one new step (plus a fixture file
+//! of the old version under `fixtures/`) rather than touching every older
+//! migration.
+//!
+//----------------------------------------------------------------------------//
+
+use crate::internal::*;
+use crate::voxel_set::VoxelSetFile;
+use std::fmt::Debug;
+
+pub struct Migration<V, T> {
+    pub from: V,
+    pub to: V,
+    pub apply: fn(T) -> Result<T, Error>,
+}
+
+pub struct MigrationRegistry<V, T> {
+    current: V,
+    migrations: Vec<Migration<V, T>>,
+}
+
+impl<V, T> MigrationRegistry<V, T>
+where
+    V: PartialEq + Debug + Clone,
+{
+    pub fn new(current: V) -> Self {
+        Self {
+            current,
+            migrations: Vec::new(),
+        }
+    }
+
+    pub fn current(&self) -> &V {
+        &self.current
+    }
+
+    pub fn register(mut self, from: V, to: V, apply: fn(T) -> Result<T, Error>) -> Self {
+        self.migrations.push(Migration { from, to, apply });
+        self
+    }
+
+    /// Applies migrations until the data is at the current version.  Returns
+    /// `Error::FileVersion` if there is no path from `version`.
+    pub fn migrate(&self, version: &V, data: T) -> Result<T, Error> {
+        let mut version = version.clone();
+        let mut data = data;
+
+        // Bound the walk so a bad registration can't loop forever
+        for _ in 0..=self.migrations.len() {
+            if version == self.current {
+                return Ok(data);
+            }
+            let Some(step) = self.migrations.iter().find(|m| m.from == version) else {
+                break;
+            };
+            data = (step.apply)(data)?;
+            version = step.to.clone();
+        }
+        Err(Error::FileVersion(format!("{:?}", version)))
+    }
+}
+
+// ----------------------------------------------------------------------------
+// VoxelSet
+// ----------------------------------------------------------------------------
+
+pub const VOXEL_SET_FILE_VERSION: [u8; 4] = [0, 0, 3, 0];
+
+/// Migrations operate on the raw bytes of the whole file.
+pub fn voxel_set_migrations() -> MigrationRegistry<[u8; 4], Vec<u8>> {
+    MigrationRegistry::new(VOXEL_SET_FILE_VERSION)
+        .register([0, 0, 1, 0], [0, 0, 2, 0], voxel_set_0_0_1_0_to_0_0_2_0)
+        .register([0, 0, 2, 0], [0, 0, 3, 0], voxel_set_0_0_2_0_to_0_0_3_0)
+}
+
+/// 0.0.2.0 added the codec id to the header.  Older files are always
+/// deflate compressed.
+fn voxel_set_0_0_1_0_to_0_0_2_0(bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
+    #[derive(Deserialize)]
+    struct VoxelSetFileV1 {
+        identifier: [u8; 8],
+        #[allow(dead_code)]
+        version: [u8; 4],
+        compressed_voxel_set: Vec<u8>,
+    }
+
+    let old: VoxelSetFileV1 =
+        deserialize_from_bytes(&bytes).map_err(|e| Error::CorruptData(e.to_string()))?;
+    serialize_to_bytes(&VoxelSetFile {
+        identifier: old.identifier,
+        version: [0, 0, 2, 0],
+        codec: Codec::Deflate.id(),
+        compressed_voxel_set: old.compressed_voxel_set,
+    })
+}
+
+/// 0.0.3.0 added metadata to the voxel set itself, so the set has to be
+/// decompressed and re-encoded.
+fn voxel_set_0_0_2_0_to_0_0_3_0(bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
+    #[derive(Deserialize)]
+    struct VoxelSetV2 {
+        generation: u64,
+        palette: VoxelPalette,
+        attributes: Vec<VoxelSetAttribute>,
+        data: HashMap<(i32, i32), HashMap<i32, PaletteIndex>>,
+    }
+    #[derive(Serialize)]
+    struct VoxelSetV3 {
+        generation: u64,
+        palette: VoxelPalette,
+        attributes: Vec<VoxelSetAttribute>,
+        metadata: VoxelSetMetadata,
+        data: HashMap<(i32, i32), HashMap<i32, PaletteIndex>>,
+    }
+
+    let file: VoxelSetFile =
+        deserialize_from_bytes(&bytes).map_err(|e| Error::CorruptData(e.to_string()))?;
+    let codec = Codec::from_id(file.codec)?;
+    let old: VoxelSetV2 = decompress_and_deserialize_with(&file.compressed_voxel_set, codec)?;
+    let new = VoxelSetV3 {
+        generation: old.generation,
+        palette: old.palette,
+        attributes: old.attributes,
+        metadata: VoxelSetMetadata::default(),
+        data: old.data,
+    };
+    let compression = Compression {
+        codec,
+        ..Compression::default()
+    };
+    serialize_to_bytes(&VoxelSetFile {
+        identifier: file.identifier,
+        version: [0, 0, 3, 0],
+        codec: file.codec,
+        compressed_voxel_set: serialize_and_compress_with(&new, &compression)?,
+    })
+}
+
+// ----------------------------------------------------------------------------
+// VoxelScene
+// ----------------------------------------------------------------------------
+
+/// Migrations operate on the untyped YAML of the `scene` field.  There are
+/// no steps yet: optional fields such as a model's orientation are added
+/// with serde defaults and don't need a new version.
+pub fn voxel_scene_migrations() -> MigrationRegistry<String, serde_yaml::Value> {
+    MigrationRegistry::new(VOXEL_SCENE_FILE_VERSION.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_registry_walks_steps() {
+        let registry = MigrationRegistry::new(3)
+            .register(2, 3, |v: Vec<i32>| Ok([v, vec![3]].concat()))
+            .register(1, 2, |v: Vec<i32>| Ok([v, vec![2]].concat()));
+
+        assert_eq!(registry.migrate(&1, vec![1]).unwrap(), vec![1, 2, 3]);
+        assert_eq!(registry.migrate(&3, vec![]).unwrap(), Vec::<i32>::new());
+        assert!(matches!(
+            registry.migrate(&0, vec![]),
+            Err(Error::FileVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_voxel_set_fixtures() {
+        for bytes in [
+            include_bytes!("../fixtures/voxel_set-0.0.1.0.vset").as_slice(),
+            include_bytes!("../fixtures/voxel_set-0.0.2.0.vset").as_slice(),
+            include_bytes!("../fixtures/voxel_set-0.0.3.0.vset").as_slice(),
+        ] {
+            let model = VoxelSet::deserialize_from_bytes(bytes).unwrap();
+            assert_eq!(model.get_voxel((0, 0, 0)).id, "stone");
+            assert_eq!(model.get_voxel((1, 0, 1)).id, "grass");
+            assert_eq!(model.voxel_iter(false).len(), 3);
+        }
+
+        let bytes = include_bytes!("../fixtures/voxel_set-0.0.3.0.vset");
+        let model = VoxelSet::deserialize_from_bytes(bytes).unwrap();
+        assert_eq!(model.anchor("entrance"), Some(IVec3::new(1, -1, 0)));
+    }
+
+    #[test]
+    fn test_voxel_scene_fixtures() {
+        let yaml = include_str!("../fixtures/voxel_scene-0.0.1.yaml");
+        let file = VoxelSceneFile::from_yaml_str(yaml).unwrap();
+        assert_eq!(file.version, VOXEL_SCENE_FILE_VERSION);
+        let models = &file.scene.layers[0].models;
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].model_id, "hill_with_road");
+        assert_eq!(models[1].position, IVec3::new(82, 0, 0));
+        assert_eq!(models[1].orientation, Orientation::identity());
+
+        let yaml = yaml.replace("version: 0.0.1", "version: 0.0.0");
+        assert!(matches!(
+            VoxelSceneFile::from_yaml_str(&yaml),
+            Err(Error::FileVersion(_))
+        ));
+    }
+}
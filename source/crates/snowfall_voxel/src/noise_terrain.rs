@@ -0,0 +1,197 @@
+//----------------------------------------------------------------------------//
+//! Unbounded terrain generated from layered noise.
+//!
+//! `NoiseTerrain` is a `VoxelGridGenerator`, so a `VoxelGrid` using it
+//! generates each chunk on demand as it is first touched, computing the
+//! surface height once per column.  Every voxel is a
+//! pure function of the seed and its world position, so chunks are
+//! deterministic and line up seamlessly no matter which order they are
+//! generated in.
+//!
+//! The surface height is the sum of three noise layers:
+//!
+//! - **continents**: very low frequency; decides land and sea and the broad
+//!   elevation of the land
+//! - **hills**: medium frequency, faded out near the coast so that beaches
+//!   stay flat
+//! - **detail**: small bumps so the ground is not perfectly smooth
+//!
+//! Surface rules then choose the blocks: sand at the shore, grass on most
+//! land, bare stone and then snow higher up, a few voxels of dirt under the
+//! surface, stone below that, and water filling everything below sea level.
+//!
+//----------------------------------------------------------------------------//
+
+use crate::internal::*;
+
+/// Z of the top water voxel
+const SEA_LEVEL: i32 = 0;
+
+/// Heights above which the surface is bare stone, then snow
+const ROCK_LINE: i32 = 44;
+const SNOW_LINE: i32 = 56;
+
+/// Voxels of dirt (or sand) between the surface and the stone
+const SOIL_DEPTH: i32 = 3;
+
+pub struct NoiseTerrain {
+    continents: NoiseGen,
+    hills: NoiseGen,
+    detail: NoiseGen,
+}
+
+impl NoiseTerrain {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = RNG::new(seed);
+        Self {
+            continents: rng.open_simplex().scale(1024.0).build(),
+            hills: rng.open_simplex().scale(96.0).build(),
+            detail: rng.open_simplex().scale(12.0).build(),
+        }
+    }
+
+    /// Z of the top solid voxel of the column, which may be under water.
+    pub fn height_at(&self, x: i32, y: i32) -> i32 {
+        let (u, v) = (x as f32, y as f32);
+
+        let c = self.continents.gen_2d(u, v);
+        let base = (c - 0.45) * 160.0;
+
+        // Hills rise from nothing at the coast to full height inland
+        let t = ((c - 0.45) / 0.2).clamp(0.0, 1.0);
+        let inland = t * t * (3.0 - 2.0 * t);
+        let hills = (self.hills.gen_2d(u, v) - 0.5) * 64.0 * inland;
+
+        let detail = (self.detail.gen_2d(u, v) - 0.5) * 4.0;
+
+        (base + hills + detail).round() as i32
+    }
+
+    /// Id of the block at the world position.
+    pub fn block_at(&self, p: IVec3) -> &'static str {
+        Self::block_in_column(self.height_at(p.x, p.y), p.z)
+    }
+
+    /// Id of the block at `z` in a column whose surface is at `height`.
+    fn block_in_column(height: i32, z: i32) -> &'static str {
+        if z > height {
+            return if z <= SEA_LEVEL { "water" } else { "empty" };
+        }
+
+        let beach = height <= SEA_LEVEL + 1;
+        let depth = height - z;
+        if depth == 0 {
+            if beach {
+                "sand"
+            } else if height > SNOW_LINE {
+                "snow"
+            } else if height > ROCK_LINE {
+                "stone"
+            } else {
+                "grass"
+            }
+        } else if depth <= SOIL_DEPTH && height <= ROCK_LINE {
+            if beach {
+                "sand"
+            } else {
+                "dirt"
+            }
+        } else {
+            "stone"
+        }
+    }
+}
+
+impl VoxelGridGenerator for NoiseTerrain {
+    fn generate(&self, world_position: IVec3) -> &str {
+        self.block_at(world_position)
+    }
+
+    fn generate_column<'a>(&'a self, base: IVec3, column: &mut [&'a str]) {
+        let height = self.height_at(base.x, base.y);
+        for (dz, block) in column.iter_mut().enumerate() {
+            *block = Self::block_in_column(height, base.z + dz as i32);
+        }
+    }
+
+    fn blocks(&self) -> Vec<Block> {
+        vec![
+            Block::color("grass", 5, 60, 10),
+            Block::color("dirt", 10, 8, 4),
+            Block::color("stone", 60, 60, 58),
+            Block::color("sand", 190, 175, 120),
+            Block::color("snow", 235, 240, 245),
+            Block::color("water", 30, 60, 130).modify(|b| b.walk_cost = 10.0),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_deterministic_and_seamless() {
+        let terrain = NoiseTerrain::new(42);
+        let same = NoiseTerrain::new(42);
+        let other = NoiseTerrain::new(43);
+
+        let mut differs = false;
+        for i in -64..64 {
+            let (x, y) = (i * 37, i * -53);
+            assert_eq!(terrain.height_at(x, y), same.height_at(x, y));
+            differs |= terrain.height_at(x, y) != other.height_at(x, y);
+
+            // No cliffs between neighboring columns, including across chunk
+            // boundaries
+            let dx = terrain.height_at(x + 1, y) - terrain.height_at(x, y);
+            let dy = terrain.height_at(x, y + 1) - terrain.height_at(x, y);
+            assert!(dx.abs() <= 4 && dy.abs() <= 4, "({}, {})", x, y);
+        }
+        assert!(differs);
+    }
+
+    #[test]
+    fn test_surface_rules() {
+        let terrain = NoiseTerrain::new(7);
+        let (mut land, mut sea) = (0, 0);
+        for i in 0..4096 {
+            let (x, y) = ((i % 64) * 64, (i / 64) * 64);
+            let h = terrain.height_at(x, y);
+            let top = terrain.block_at(IVec3::new(x, y, h));
+            assert_eq!(terrain.block_at(IVec3::new(x, y, h - 8)), "stone");
+            if h < SEA_LEVEL {
+                sea += 1;
+                assert_eq!(top, "sand");
+                assert_eq!(terrain.block_at(IVec3::new(x, y, SEA_LEVEL)), "water");
+            } else {
+                land += 1;
+                assert_eq!(terrain.block_at(IVec3::new(x, y, h + 1)), "empty");
+            }
+        }
+        assert!(land > 0 && sea > 0, "land {} sea {}", land, sea);
+    }
+
+    #[test]
+    fn test_grid_generates_chunks() {
+        let terrain = NoiseTerrain::new(1);
+        let (x, y) = (1000, -2000);
+        let height = terrain.height_at(x, y);
+        let expected = terrain.block_at(IVec3::new(x, y, height));
+
+        let mut grid = VoxelGrid::new().with_generator(terrain);
+        assert!(!grid.is_empty(IVec3::new(x, y, height)));
+        assert_eq!(grid.get((x, y, height)).unwrap().id, expected);
+        assert!(grid.is_empty(IVec3::new(x, y, height.max(SEA_LEVEL) + 1)));
+
+        // Chunks generated a column at a time match the per-voxel rules
+        let terrain = NoiseTerrain::new(1);
+        for z in height - 20..height + 20 {
+            let p = IVec3::new(x, y, z);
+            // is_empty generates the chunk if needed; get does not
+            grid.is_empty(p);
+            let id = grid.get(p).map_or("empty", |b| b.id.as_str());
+            assert_eq!(id, terrain.block_at(p), "z = {}", z);
+        }
+    }
+}
@@ -26,6 +26,16 @@ pub struct GenContext {
     pub seed: u64,
     pub center: IVec3,
     pub params: serde_json::Value,
+
+    /// Directory that generators load image and other asset files from
+    pub asset_root: std::path::PathBuf,
+
+    /// Cache consulted by `generate_model` for generators with cacheable
+    /// output.  None disables caching.
+    pub cache: Option<GenCache>,
+
+    /// Generators available to `generate_model`
+    pub registry: std::sync::Arc<GeneratorRegistry>,
 }
 
 impl GenContext {
@@ -38,9 +48,17 @@ impl GenContext {
             seed,
             center: IVec3::new(0, 0, 0),
             params: serde_json::Value::Null,
+            asset_root: std::path::PathBuf::from("./assets"),
+            cache: None,
+            registry: GeneratorRegistry::builtin(),
         }
     }
 
+    /// Context for running another generator.  The fork starts with no
+    /// params (`Null`) rather than a copy of this context's params: those
+    /// belong to this context's generator, and the forked generator would
+    /// fail validation on fields it doesn't know.  Use `with_params` to give
+    /// the fork its own.
     pub fn fork<T>(&self, generator: T, seed: u64) -> Self
     where
         T: Into<String>,
@@ -49,7 +67,10 @@ impl GenContext {
             generator: generator.into(),
             seed,
             center: self.center,
-            params: self.params.clone(),
+            params: serde_json::Value::Null,
+            asset_root: self.asset_root.clone(),
+            cache: self.cache.clone(),
+            registry: self.registry.clone(),
         }
     }
     pub fn with_params(&self, params: serde_json::Value) -> Self {
@@ -58,6 +79,9 @@ impl GenContext {
             seed: self.seed,
             center: self.center,
             params,
+            asset_root: self.asset_root.clone(),
+            cache: self.cache.clone(),
+            registry: self.registry.clone(),
         }
     }
     pub fn with_center(&self, center: IVec3) -> Self {
@@ -66,19 +90,70 @@ impl GenContext {
             seed: self.seed,
             center,
             params: self.params.clone(),
+            asset_root: self.asset_root.clone(),
+            cache: self.cache.clone(),
+            registry: self.registry.clone(),
         }
     }
 
+    pub fn with_asset_root<P>(&self, asset_root: P) -> Self
+    where
+        P: Into<std::path::PathBuf>,
+    {
+        Self {
+            generator: self.generator.clone(),
+            seed: self.seed,
+            center: self.center,
+            params: self.params.clone(),
+            asset_root: asset_root.into(),
+            cache: self.cache.clone(),
+            registry: self.registry.clone(),
+        }
+    }
+
+    pub fn with_cache(&self, cache: GenCache) -> Self {
+        Self {
+            generator: self.generator.clone(),
+            seed: self.seed,
+            center: self.center,
+            params: self.params.clone(),
+            asset_root: self.asset_root.clone(),
+            cache: Some(cache),
+            registry: self.registry.clone(),
+        }
+    }
+
+    pub fn with_registry(&self, registry: std::sync::Arc<GeneratorRegistry>) -> Self {
+        Self {
+            generator: self.generator.clone(),
+            seed: self.seed,
+            center: self.center,
+            params: self.params.clone(),
+            asset_root: self.asset_root.clone(),
+            cache: self.cache.clone(),
+            registry,
+        }
+    }
+
+    /// Options for loading sprites relative to the context's asset root
+    pub fn sprite_options(&self) -> SpriteOptions {
+        SpriteOptions::default().with_asset_root(self.asset_root.clone())
+    }
+
     pub fn to_object(&self, model: VoxelSet) -> Object {
         Object {
             generator_id: self.generator.clone(),
             seed: self.seed,
             params: self.params.clone(),
             position: self.center,
+            orientation: Orientation::default(),
             imp: ObjectImp::VoxelSet(Box::new(model)),
         }
     }
 
+    /// Deserializes the params, falling back to the default on error.
+    /// `generate_model` validates params against the generator's schema
+    /// before running it, so errors are reported there.
     pub fn params<T>(&self) -> T
     where
         T: serde::de::DeserializeOwned + Default,
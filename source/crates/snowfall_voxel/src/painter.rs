@@ -0,0 +1,527 @@
+//----------------------------------------------------------------------------//
+//! Shapes and block selection for building procedural models.
+//!
+//! A `Painter` draws shapes into a `VoxelSet`.  Each shape takes a
+//! `BlockSelector` that chooses the block for every voxel the shape covers,
+//! so material variation (random, noise-driven, or by height) is described
+//! once rather than inside every loop:
+//!
+//! ```ignore
+//! let mut wood = BlockSelector::uniform(&mut rng, &["wood", "wood2"]);
+//! let mut leaves = BlockSelector::gradient(8, 24, &[(0.5, "leaves"), (1.0, "leaves2")]);
+//!
+//! let mut painter = Painter::new(&mut model);
+//! painter.line(IVec3::ZERO, IVec3::new(0, 0, 10), 1.0, &mut wood);
+//! painter.sphere(IVec3::new(0, 0, 14), 6.0, &mut leaves);
+//! ```
+//!
+//! Shapes paint each covered voxel once, in Z, Y, X order, so selectors that
+//! draw from an RNG are deterministic.  The painter's mode and mask restrict
+//! which voxels are painted.
+//!
+//----------------------------------------------------------------------------//
+
+use crate::internal::*;
+
+/// Chooses the block for each painted voxel.  Block ids that are not in the
+/// target's palette paint empty voxels.
+pub enum BlockSelector {
+    /// Always the same block
+    Block(String),
+
+    /// Random choice per voxel
+    Uniform { rng: RNG, ids: Vec<String> },
+
+    /// Random choice per voxel by weight
+    Weighted {
+        rng: RNG,
+        choices: Vec<(u32, String)>,
+    },
+
+    /// The first band whose threshold is above the noise value at the voxel
+    /// (the last band if none are)
+    Noise {
+        noise: NoiseGen,
+        bands: Vec<(f32, String)>,
+    },
+
+    /// The first stop whose threshold is at or above the voxel's height,
+    /// where `min_z` is 0.0 and `max_z` is 1.0 (the last stop if none are)
+    Gradient {
+        min_z: i32,
+        max_z: i32,
+        stops: Vec<(f32, String)>,
+    },
+}
+
+impl BlockSelector {
+    pub fn block<S>(id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        BlockSelector::Block(id.into())
+    }
+
+    /// Draws the same sequence as `RNG::select_fn` for the same RNG state.
+    pub fn uniform(rng: &mut RNG, ids: &[&str]) -> Self {
+        assert!(!ids.is_empty(), "selector needs at least one block");
+        BlockSelector::Uniform {
+            rng: rng.fork(),
+            ids: ids.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+
+    pub fn weighted(rng: &mut RNG, choices: &[(u32, &str)]) -> Self {
+        BlockSelector::Weighted {
+            rng: rng.fork(),
+            choices: choices.iter().map(|(w, id)| (*w, id.to_string())).collect(),
+        }
+    }
+
+    /// Noise is sampled at the voxel's coordinates, so the noise scale is in
+    /// voxels.
+    pub fn noise(noise: NoiseGen, bands: &[(f32, &str)]) -> Self {
+        BlockSelector::Noise {
+            noise,
+            bands: to_owned_stops(bands),
+        }
+    }
+
+    pub fn gradient(min_z: i32, max_z: i32, stops: &[(f32, &str)]) -> Self {
+        BlockSelector::Gradient {
+            min_z,
+            max_z,
+            stops: to_owned_stops(stops),
+        }
+    }
+
+    pub fn select(&mut self, p: IVec3) -> &str {
+        match self {
+            BlockSelector::Block(id) => id,
+            BlockSelector::Uniform { rng, ids } => rng.select(ids).as_str(),
+            BlockSelector::Weighted { rng, choices } => rng.select_weighted(choices).as_str(),
+            BlockSelector::Noise { noise, bands } => {
+                let n = noise.gen_3d(p.x as f32, p.y as f32, p.z as f32);
+                select_stop(bands, n)
+            }
+            BlockSelector::Gradient {
+                min_z,
+                max_z,
+                stops,
+            } => {
+                let range = (*max_z - *min_z).max(1) as f32;
+                let t = ((p.z - *min_z) as f32 / range).clamp(0.0, 1.0);
+                select_stop(stops, t)
+            }
+        }
+    }
+}
+
+fn to_owned_stops(stops: &[(f32, &str)]) -> Vec<(f32, String)> {
+    assert!(!stops.is_empty(), "selector needs at least one block");
+    stops.iter().map(|(t, id)| (*t, id.to_string())).collect()
+}
+
+fn select_stop(stops: &[(f32, String)], value: f32) -> &str {
+    stops
+        .iter()
+        .find(|(t, _)| value <= *t)
+        .unwrap_or(stops.last().unwrap())
+        .1
+        .as_str()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaintMode {
+    /// Paint every covered voxel
+    #[default]
+    Overwrite,
+    /// Only paint voxels that are empty
+    Fill,
+    /// Only paint voxels that are not empty
+    Replace,
+}
+
+pub struct Painter<'a> {
+    target: &'a mut VoxelSet,
+    mode: PaintMode,
+    mask: Option<Box<dyn Fn(IVec3) -> bool + 'a>>,
+}
+
+impl<'a> Painter<'a> {
+    pub fn new(target: &'a mut VoxelSet) -> Self {
+        Self {
+            target,
+            mode: PaintMode::Overwrite,
+            mask: None,
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: PaintMode) {
+        self.mode = mode;
+    }
+
+    /// Only paints voxels for which the mask returns true.
+    pub fn set_mask<F>(&mut self, mask: F)
+    where
+        F: Fn(IVec3) -> bool + 'a,
+    {
+        self.mask = Some(Box::new(mask));
+    }
+
+    pub fn clear_mask(&mut self) {
+        self.mask = None;
+    }
+
+    // ------------------------------------------------------------------------
+    // Shapes
+    // ------------------------------------------------------------------------
+
+    pub fn paint(&mut self, p: IVec3, selector: &mut BlockSelector) {
+        if let Some(mask) = &self.mask {
+            if !mask(p) {
+                return;
+            }
+        }
+        match self.mode {
+            PaintMode::Overwrite => {}
+            PaintMode::Fill if !self.target.is_empty(p) => return,
+            PaintMode::Replace if self.target.is_empty(p) => return,
+            _ => {}
+        }
+        let id = selector.select(p);
+        self.target.set_voxel(p, id);
+    }
+
+    /// Inclusive box from min to max.
+    pub fn cuboid<P>(&mut self, min: P, max: P, selector: &mut BlockSelector)
+    where
+        P: Into<IVec3>,
+    {
+        let (min, max) = (min.into(), max.into());
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    self.paint(IVec3::new(x, y, z), selector);
+                }
+            }
+        }
+    }
+
+    pub fn sphere(&mut self, center: IVec3, radius: f32, selector: &mut BlockSelector) {
+        self.ellipsoid(center, Vec3::splat(radius), selector);
+    }
+
+    pub fn ellipsoid(&mut self, center: IVec3, radii: Vec3, selector: &mut BlockSelector) {
+        self.rounded_shape(center, radii, 0.0, |_| 1.0, selector);
+    }
+
+    /// Ellipsoid whose surface is displaced by noise, rotated `angle` radians
+    /// about Z.  The noise is sampled in the ellipsoid's unit coordinates;
+    /// `roughness` from 0.0 to 1.0 is how much of the radius it can remove.
+    pub fn blob(
+        &mut self,
+        center: IVec3,
+        radii: Vec3,
+        angle: f32,
+        noise: &NoiseGen,
+        roughness: f32,
+        selector: &mut BlockSelector,
+    ) {
+        self.rounded_shape(
+            center,
+            radii,
+            angle,
+            |u| 1.0 - roughness + roughness * noise.gen_3d(u.x, u.y, u.z),
+            selector,
+        );
+    }
+
+    /// Vertical cylinder with `height` layers starting at `base`.
+    pub fn cylinder(
+        &mut self,
+        base: IVec3,
+        radius: f32,
+        height: i32,
+        selector: &mut BlockSelector,
+    ) {
+        self.column(base, height, |_| radius, selector);
+    }
+
+    /// Vertical cone with `height` layers whose radius shrinks linearly from
+    /// `radius` at `base` toward a point.
+    pub fn cone(&mut self, base: IVec3, radius: f32, height: i32, selector: &mut BlockSelector) {
+        self.column(
+            base,
+            height,
+            |i| radius * (height - i) as f32 / height as f32,
+            selector,
+        );
+    }
+
+    /// Vertical solid of revolution with `height` layers starting at `base`.
+    /// `radius_at` gives the radius of each layer, counting up from 0.
+    pub fn column<F>(
+        &mut self,
+        base: IVec3,
+        height: i32,
+        radius_at: F,
+        selector: &mut BlockSelector,
+    ) where
+        F: Fn(i32) -> f32,
+    {
+        for i in 0..height {
+            let radius = radius_at(i);
+            let r = radius.ceil() as i32;
+            for dy in -r..=r {
+                for dx in -r..=r {
+                    if (dx * dx + dy * dy) as f32 > radius * radius {
+                        continue;
+                    }
+                    self.paint(base + IVec3::new(dx, dy, i), selector);
+                }
+            }
+        }
+    }
+
+    /// Line from p to q, thickened to a tube of the given radius.  A radius
+    /// of zero draws a one voxel wide line.
+    pub fn line(&mut self, p: IVec3, q: IVec3, radius: f32, selector: &mut BlockSelector) {
+        let mut points = PointCollector::default();
+        for c in bresenham3d(p, q) {
+            points.add_sphere(c, radius);
+        }
+        points.paint(self, selector);
+    }
+
+    /// Tube along the Bezier curve through the control points, with the
+    /// radius varying linearly from `radius.0` to `radius.1`.
+    pub fn bezier_tube(
+        &mut self,
+        control: &[Vec3],
+        radius: (f32, f32),
+        selector: &mut BlockSelector,
+    ) {
+        if control.is_empty() {
+            return;
+        }
+
+        // Sample at least twice per voxel of the control polygon's length,
+        // which is never shorter than the curve
+        let length: f32 = control.windows(2).map(|w| w[0].distance(w[1])).sum();
+        let steps = (length * 2.0).ceil().max(1.0) as usize;
+
+        let mut points = PointCollector::default();
+        for i in 0..=steps {
+            let t = i as f32 / steps as f32;
+            let c = bezier_point(control, t).round().as_ivec3();
+            points.add_sphere(c, radius.0 + (radius.1 - radius.0) * t);
+        }
+        points.paint(self, selector);
+    }
+
+    // ------------------------------------------------------------------------
+    // Helpers
+    // ------------------------------------------------------------------------
+
+    /// Paints the voxels whose offset from the center, scaled to the unit
+    /// sphere and rotated by `-angle` about Z, is within `radius_at`.
+    fn rounded_shape<F>(
+        &mut self,
+        center: IVec3,
+        radii: Vec3,
+        angle: f32,
+        radius_at: F,
+        selector: &mut BlockSelector,
+    ) where
+        F: Fn(Vec3) -> f32,
+    {
+        let radii = radii.max(Vec3::splat(0.5));
+        let extent_xy = if angle == 0.0 {
+            radii.truncate()
+        } else {
+            Vec2::splat(radii.x.max(radii.y))
+        };
+        let extent = extent_xy.extend(radii.z).ceil().as_ivec3();
+
+        for dz in -extent.z..=extent.z {
+            for dy in -extent.y..=extent.y {
+                for dx in -extent.x..=extent.x {
+                    let (x, y) = rotate_2d(dx as f32, dy as f32, -angle);
+                    let u = Vec3::new(x, y, dz as f32) / radii;
+                    let r = radius_at(u);
+                    if u.length_squared() > r * r {
+                        continue;
+                    }
+                    self.paint(center + IVec3::new(dx, dy, dz), selector);
+                }
+            }
+        }
+    }
+}
+
+/// De Casteljau evaluation of the Bezier curve at t.
+fn bezier_point(control: &[Vec3], t: f32) -> Vec3 {
+    let mut points = control.to_vec();
+    for n in (1..points.len()).rev() {
+        for i in 0..n {
+            points[i] = points[i].lerp(points[i + 1], t);
+        }
+    }
+    points[0]
+}
+
+/// Unions overlapping stamps so each voxel is painted once.
+#[derive(Default)]
+struct PointCollector {
+    points: std::collections::HashSet<IVec3>,
+}
+
+impl PointCollector {
+    fn add_sphere(&mut self, center: IVec3, radius: f32) {
+        let r = radius.max(0.0).ceil() as i32;
+        for dz in -r..=r {
+            for dy in -r..=r {
+                for dx in -r..=r {
+                    if (dx * dx + dy * dy + dz * dz) as f32 <= radius * radius {
+                        self.points.insert(center + IVec3::new(dx, dy, dz));
+                    }
+                }
+            }
+        }
+    }
+
+    fn paint(self, painter: &mut Painter, selector: &mut BlockSelector) {
+        let mut points: Vec<IVec3> = self.points.into_iter().collect();
+        points.sort_by_key(|p| (p.z, p.y, p.x));
+        for p in points {
+            painter.paint(p, selector);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn target() -> VoxelSet {
+        let mut model = VoxelSet::new();
+        model.register_block(Block::color("stone", 80, 80, 80));
+        model.register_block(Block::color("dirt", 10, 8, 4));
+        model
+    }
+
+    fn count(model: &VoxelSet, id: &str) -> usize {
+        model
+            .voxel_iter(false)
+            .iter()
+            .filter(|(_, b)| b.id == id)
+            .count()
+    }
+
+    #[test]
+    fn test_shapes() {
+        let mut stone = BlockSelector::block("stone");
+
+        let mut model = target();
+        Painter::new(&mut model).sphere(IVec3::ZERO, 2.0, &mut stone);
+        // 1 + 6 + 12 + 8 + 6 voxels within a distance of 2
+        assert_eq!(count(&model, "stone"), 33);
+
+        let mut model = target();
+        Painter::new(&mut model).cylinder(IVec3::new(5, 5, 0), 1.0, 4, &mut stone);
+        assert_eq!(count(&model, "stone"), 20);
+        assert_eq!(model.height_at(5, 5), Some(3));
+
+        let mut model = target();
+        Painter::new(&mut model).cone(IVec3::ZERO, 3.0, 3, &mut stone);
+        assert_eq!(model.bounds().max, IVec3::new(3, 3, 2));
+        assert_eq!(count(&model, "stone"), 29 + 13 + 5);
+
+        let mut model = target();
+        Painter::new(&mut model).line(IVec3::ZERO, IVec3::new(10, 0, 0), 0.0, &mut stone);
+        assert_eq!(count(&model, "stone"), 11);
+
+        // A straight Bezier with a constant radius matches a thick line
+        let mut line = target();
+        Painter::new(&mut line).line(IVec3::ZERO, IVec3::new(0, 8, 0), 1.0, &mut stone);
+        let mut tube = target();
+        Painter::new(&mut tube).bezier_tube(
+            &[
+                Vec3::ZERO,
+                Vec3::new(0.0, 4.0, 0.0),
+                Vec3::new(0.0, 8.0, 0.0),
+            ],
+            (1.0, 1.0),
+            &mut stone,
+        );
+        assert_eq!(count(&line, "stone"), count(&tube, "stone"));
+        assert_eq!(line.bounds(), tube.bounds());
+    }
+
+    #[test]
+    fn test_blob_is_within_ellipsoid() {
+        let mut rng = RNG::new(7);
+        let noise = rng.open_simplex().scale(0.25).build();
+        let radii = Vec3::new(6.0, 3.0, 2.0);
+
+        let mut blob = target();
+        let mut stone = BlockSelector::block("stone");
+        Painter::new(&mut blob).blob(IVec3::ZERO, radii, 0.0, &noise, 0.5, &mut stone);
+        let mut ellipsoid = target();
+        Painter::new(&mut ellipsoid).ellipsoid(IVec3::ZERO, radii, &mut stone);
+
+        let n = count(&blob, "stone");
+        assert!(n > 0 && n < count(&ellipsoid, "stone"));
+        for (p, _) in blob.voxel_iter(false) {
+            assert!(!ellipsoid.is_empty(p));
+        }
+    }
+
+    #[test]
+    fn test_selectors() {
+        let mut rng = RNG::new(3);
+        let mut select_fn = rng.select_fn(vec!["stone", "dirt"]);
+        let expected: Vec<&str> = (0..16).map(|_| select_fn()).collect();
+        let mut rng = RNG::new(3);
+        let mut uniform = BlockSelector::uniform(&mut rng, &["stone", "dirt"]);
+        let actual: Vec<String> = (0..16)
+            .map(|_| uniform.select(IVec3::ZERO).to_string())
+            .collect();
+        assert_eq!(actual, expected);
+
+        let mut gradient = BlockSelector::gradient(0, 10, &[(0.5, "dirt"), (1.0, "stone")]);
+        assert_eq!(gradient.select(IVec3::new(0, 0, -4)), "dirt");
+        assert_eq!(gradient.select(IVec3::new(0, 0, 5)), "dirt");
+        assert_eq!(gradient.select(IVec3::new(0, 0, 6)), "stone");
+        assert_eq!(gradient.select(IVec3::new(0, 0, 40)), "stone");
+
+        let noise = RNG::new(5).open_simplex().scale(4.0).build();
+        let mut noise = BlockSelector::noise(noise, &[(0.5, "dirt"), (1.0, "stone")]);
+        let mut model = target();
+        Painter::new(&mut model).cuboid((0, 0, 0), (15, 15, 0), &mut noise);
+        assert!(count(&model, "dirt") > 0 && count(&model, "stone") > 0);
+    }
+
+    #[test]
+    fn test_mode_and_mask() {
+        let mut model = target();
+        let mut painter = Painter::new(&mut model);
+        painter.cuboid((0, 0, 0), (3, 0, 0), &mut BlockSelector::block("stone"));
+
+        painter.set_mode(PaintMode::Fill);
+        painter.cuboid((2, 0, 0), (5, 0, 0), &mut BlockSelector::block("dirt"));
+        painter.set_mode(PaintMode::Replace);
+        painter.set_mask(|p| p.x % 2 == 0);
+        painter.cuboid((0, 0, 0), (9, 0, 0), &mut BlockSelector::block("dirt"));
+        drop(painter);
+
+        let ids: Vec<String> = (0..7)
+            .map(|x| model.get_voxel((x, 0, 0)).id.clone())
+            .collect();
+        assert_eq!(
+            ids,
+            ["dirt", "stone", "dirt", "stone", "dirt", "dirt", "empty"]
+        );
+    }
+}
@@ -0,0 +1,337 @@
+//----------------------------------------------------------------------------//
+//! Description of generator params used both to validate params before a
+//! generator runs and to export JSON Schema for editors.
+//!
+//! Objects are closed: fields not listed in the schema are errors, which is
+//! what catches typos in scene files.  Optional fields also accept `null`
+//! since that is how an unset `Option` serializes.
+//!
+//----------------------------------------------------------------------------//
+
+use crate::internal::*;
+use serde::de::DeserializeOwned;
+
+/// Params types implement this so generators can describe and validate them.
+pub trait GeneratorParams: Serialize + DeserializeOwned + Default {
+    fn schema() -> ParamSchema;
+
+    /// Checks constraints the schema can't describe, such as a minimum that
+    /// must not exceed a maximum.  Errors start with the field name.
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ParamSchema {
+    Any,
+    Bool,
+    Integer {
+        min: Option<i64>,
+        max: Option<i64>,
+    },
+    Number,
+    String,
+    /// One of a fixed set of strings
+    Enum(&'static [&'static str]),
+    Array(Box<ParamSchema>),
+    Tuple(Vec<ParamSchema>),
+    Object(Vec<ParamField>),
+}
+
+#[derive(Debug, Clone)]
+pub struct ParamField {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub required: bool,
+    pub schema: ParamSchema,
+}
+
+impl ParamField {
+    pub fn required(name: &'static str, description: &'static str, schema: ParamSchema) -> Self {
+        Self {
+            name,
+            description,
+            required: true,
+            schema,
+        }
+    }
+
+    pub fn optional(name: &'static str, description: &'static str, schema: ParamSchema) -> Self {
+        Self {
+            name,
+            description,
+            required: false,
+            schema,
+        }
+    }
+}
+
+impl ParamSchema {
+    pub fn integer() -> Self {
+        ParamSchema::Integer {
+            min: None,
+            max: None,
+        }
+    }
+
+    pub fn integer_range(min: i64, max: i64) -> Self {
+        ParamSchema::Integer {
+            min: Some(min),
+            max: Some(max),
+        }
+    }
+
+    pub fn array(items: ParamSchema) -> Self {
+        ParamSchema::Array(Box::new(items))
+    }
+
+    /// `[r, g, b]` with each component 0-255.
+    pub fn rgb() -> Self {
+        ParamSchema::Tuple(vec![ParamSchema::integer_range(0, 255); 3])
+    }
+
+    /// Checks the value against the schema.  The error names the path to the
+    /// offending value, starting with `path`.
+    pub fn validate(&self, value: &serde_json::Value, path: &str) -> Result<(), String> {
+        use serde_json::Value;
+
+        let mismatch = |expected: &str| {
+            Err(format!(
+                "{}: expected {}, found {}",
+                path,
+                expected,
+                json_type_name(value)
+            ))
+        };
+
+        match self {
+            ParamSchema::Any => Ok(()),
+            ParamSchema::Bool => match value {
+                Value::Bool(_) => Ok(()),
+                _ => mismatch("a boolean"),
+            },
+            ParamSchema::Integer { min, max } => {
+                let Some(n) = value.as_i64() else {
+                    return mismatch("an integer");
+                };
+                if min.is_some_and(|min| n < min) || max.is_some_and(|max| n > max) {
+                    return Err(format!(
+                        "{}: {} is outside the range {}..={}",
+                        path,
+                        n,
+                        min.map_or(String::new(), |v| v.to_string()),
+                        max.map_or(String::new(), |v| v.to_string()),
+                    ));
+                }
+                Ok(())
+            }
+            ParamSchema::Number => match value {
+                Value::Number(_) => Ok(()),
+                _ => mismatch("a number"),
+            },
+            ParamSchema::String => match value {
+                Value::String(_) => Ok(()),
+                _ => mismatch("a string"),
+            },
+            ParamSchema::Enum(names) => match value {
+                Value::String(s) if names.contains(&s.as_str()) => Ok(()),
+                Value::String(s) => Err(format!(
+                    "{}: unknown value \"{}\" (expected one of: {})",
+                    path,
+                    s,
+                    names.join(", ")
+                )),
+                _ => mismatch("a string"),
+            },
+            ParamSchema::Array(items) => {
+                let Value::Array(values) = value else {
+                    return mismatch("an array");
+                };
+                for (i, v) in values.iter().enumerate() {
+                    items.validate(v, &format!("{}[{}]", path, i))?;
+                }
+                Ok(())
+            }
+            ParamSchema::Tuple(items) => {
+                let Value::Array(values) = value else {
+                    return mismatch(&format!("an array of {}", items.len()));
+                };
+                if values.len() != items.len() {
+                    return Err(format!(
+                        "{}: expected {} elements, found {}",
+                        path,
+                        items.len(),
+                        values.len()
+                    ));
+                }
+                for (i, (schema, v)) in items.iter().zip(values).enumerate() {
+                    schema.validate(v, &format!("{}[{}]", path, i))?;
+                }
+                Ok(())
+            }
+            ParamSchema::Object(fields) => {
+                let Value::Object(map) = value else {
+                    return mismatch("an object");
+                };
+                for key in map.keys() {
+                    if !fields.iter().any(|f| f.name == key) {
+                        let names: Vec<&str> = fields.iter().map(|f| f.name).collect();
+                        return Err(format!(
+                            "{}.{}: unknown field (expected one of: {})",
+                            path,
+                            key,
+                            names.join(", ")
+                        ));
+                    }
+                }
+                for field in fields {
+                    let field_path = format!("{}.{}", path, field.name);
+                    match map.get(field.name) {
+                        None | Some(Value::Null) if field.required => {
+                            return Err(format!("{}: missing required field", field_path));
+                        }
+                        None | Some(Value::Null) => {}
+                        Some(v) => field.schema.validate(v, &field_path)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub fn to_json_schema(&self) -> serde_json::Value {
+        use serde_json::json;
+
+        match self {
+            ParamSchema::Any => json!({}),
+            ParamSchema::Bool => json!({ "type": "boolean" }),
+            ParamSchema::Integer { min, max } => {
+                let mut schema = json!({ "type": "integer" });
+                if let Some(min) = min {
+                    schema["minimum"] = json!(min);
+                }
+                if let Some(max) = max {
+                    schema["maximum"] = json!(max);
+                }
+                schema
+            }
+            ParamSchema::Number => json!({ "type": "number" }),
+            ParamSchema::String => json!({ "type": "string" }),
+            ParamSchema::Enum(names) => json!({ "type": "string", "enum": names }),
+            ParamSchema::Array(items) => json!({
+                "type": "array",
+                "items": items.to_json_schema(),
+            }),
+            ParamSchema::Tuple(items) => json!({
+                "type": "array",
+                "prefixItems": items.iter().map(|s| s.to_json_schema()).collect::<Vec<_>>(),
+                "items": false,
+                "minItems": items.len(),
+            }),
+            ParamSchema::Object(fields) => {
+                let mut properties = serde_json::Map::new();
+                for field in fields {
+                    let mut schema = field.schema.to_json_schema();
+                    if !field.required {
+                        schema = json!({ "anyOf": [schema, { "type": "null" }] });
+                    }
+                    schema["description"] = json!(field.description);
+                    properties.insert(field.name.to_string(), schema);
+                }
+                let required: Vec<&str> = fields
+                    .iter()
+                    .filter(|f| f.required)
+                    .map(|f| f.name)
+                    .collect();
+                json!({
+                    "type": "object",
+                    "properties": properties,
+                    "required": required,
+                    "additionalProperties": false,
+                })
+            }
+        }
+    }
+}
+
+fn json_type_name(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "a boolean",
+        serde_json::Value::Number(_) => "a number",
+        serde_json::Value::String(_) => "a string",
+        serde_json::Value::Array(_) => "an array",
+        serde_json::Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> ParamSchema {
+        ParamSchema::Object(vec![
+            ParamField::optional(
+                "count",
+                "",
+                ParamSchema::Tuple(vec![ParamSchema::integer(); 2]),
+            ),
+            ParamField::optional(
+                "generators",
+                "",
+                ParamSchema::array(ParamSchema::Tuple(vec![
+                    ParamSchema::integer_range(0, 100),
+                    ParamSchema::String,
+                ])),
+            ),
+            ParamField::required("path", "", ParamSchema::String),
+            ParamField::optional("mode", "", ParamSchema::Enum(&["cut", "fill"])),
+        ])
+    }
+
+    #[test]
+    fn test_validate_paths() {
+        let schema = schema();
+        let ok = json!({ "path": "a.yaml", "count": [1, 2], "generators": [[10, "tree1"]] });
+        assert!(schema.validate(&ok, "params").is_ok());
+        assert!(schema
+            .validate(&json!({ "path": "a", "count": null }), "params")
+            .is_ok());
+
+        let err = |value| schema.validate(&value, "params").unwrap_err();
+        assert!(
+            err(json!({ "path": "a", "cuont": [1, 2] })).starts_with("params.cuont: unknown field")
+        );
+        assert_eq!(err(json!({})), "params.path: missing required field");
+        assert_eq!(
+            err(json!({ "path": "a", "generators": [[10, 5]] })),
+            "params.generators[0][1]: expected a string, found a number"
+        );
+        assert_eq!(
+            err(json!({ "path": "a", "generators": [[200, "tree1"]] })),
+            "params.generators[0][0]: 200 is outside the range 0..=100"
+        );
+        assert_eq!(
+            err(json!({ "path": "a", "mode": "fil" })),
+            "params.mode: unknown value \"fil\" (expected one of: cut, fill)"
+        );
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let schema = schema().to_json_schema();
+        assert_eq!(schema["additionalProperties"], json!(false));
+        assert_eq!(schema["required"], json!(["path"]));
+        assert_eq!(schema["properties"]["path"]["type"], json!("string"));
+        assert_eq!(
+            schema["properties"]["mode"]["anyOf"][0]["enum"],
+            json!(["cut", "fill"])
+        );
+        assert_eq!(
+            schema["properties"]["count"]["anyOf"][0]["prefixItems"][1]["type"],
+            json!("integer")
+        );
+    }
+}
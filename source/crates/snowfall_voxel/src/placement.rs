@@ -0,0 +1,353 @@
+//----------------------------------------------------------------------------//
+//! Spatial index of the world-space footprints of placed objects.
+//!
+//! Each `Scene2` keeps a `PlacementIndex` so that generators placing objects
+//! can avoid objects placed by other generators, not just the ones they
+//! placed themselves.  Areas of terrain can also be reserved (e.g. for
+//! buildings) so nothing is placed on them.
+//!
+//! The index buckets footprints into a 2D grid of cells on X/Y, so queries
+//! only test the footprints near the candidate.
+//!
+//----------------------------------------------------------------------------//
+
+use crate::internal::*;
+
+const CELL_SIZE: i32 = 16;
+
+#[derive(Debug, Clone)]
+pub struct Placement {
+    pub bounds: IBox3,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlacementOptions {
+    /// Extra X/Y clearance required around the footprint
+    pub margin: i32,
+
+    /// Maximum X/Y distance a candidate may be moved to find a free spot.
+    /// Zero means candidates are only accepted or rejected.
+    pub max_nudge: i32,
+}
+
+impl PlacementOptions {
+    pub fn with_margin(mut self, margin: i32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    pub fn with_max_nudge(mut self, max_nudge: i32) -> Self {
+        self.max_nudge = max_nudge;
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct PlacementIndex {
+    placements: Vec<Placement>,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl PlacementIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.placements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.placements.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Placement> {
+        self.placements.iter()
+    }
+
+    /// Records a footprint without checking for intersections.
+    pub fn insert<S>(&mut self, bounds: IBox3, label: S)
+    where
+        S: Into<String>,
+    {
+        if bounds.is_empty() {
+            return;
+        }
+        let index = self.placements.len();
+        for cell in cells_of(&bounds) {
+            self.cells.entry(cell).or_default().push(index);
+        }
+        self.placements.push(Placement {
+            bounds,
+            label: label.into(),
+        });
+    }
+
+    /// Removes the placements recorded after the index held `len` of them,
+    /// e.g. to undo the placements made while generating a model.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.placements.len() {
+            return;
+        }
+        for placement in self.placements[len..].iter() {
+            for cell in cells_of(&placement.bounds) {
+                if let Some(indices) = self.cells.get_mut(&cell) {
+                    indices.retain(|&i| i < len);
+                    if indices.is_empty() {
+                        self.cells.remove(&cell);
+                    }
+                }
+            }
+        }
+        self.placements.truncate(len);
+    }
+
+    /// Reserves the full height of the columns from `min` to `max`
+    /// (inclusive) so nothing is placed there.
+    pub fn reserve<S>(&mut self, min: IVec2, max: IVec2, label: S)
+    where
+        S: Into<String>,
+    {
+        // Not i32::MIN/MAX so that translating the box can't overflow
+        const HALF_RANGE: i32 = i32::MAX / 4;
+        self.insert(
+            IBox3::from_min_max(
+                IVec3::new(min.x, min.y, -HALF_RANGE),
+                IVec3::new(max.x, max.y, HALF_RANGE),
+            ),
+            label,
+        );
+    }
+
+    /// Returns the placements intersecting the bounds.
+    pub fn query(&self, bounds: &IBox3) -> Vec<&Placement> {
+        let mut indices: Vec<usize> = cells_of(bounds)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .filter(|&i| self.placements[i].bounds.intersects(bounds))
+            .collect();
+        indices.sort();
+        indices.dedup();
+        indices.into_iter().map(|i| &self.placements[i]).collect()
+    }
+
+    pub fn intersects(&self, bounds: &IBox3) -> bool {
+        cells_of(bounds)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .any(|&i| self.placements[i].bounds.intersects(bounds))
+    }
+
+    /// Finds the nearest X/Y offset, within `options.max_nudge`, at which
+    /// none of the footprint boxes intersect existing placements.  The boxes
+    /// are recorded at that offset and the offset is returned, or None is
+    /// returned if there is no free spot.
+    pub fn place<S>(
+        &mut self,
+        footprint: &[IBox3],
+        label: S,
+        options: &PlacementOptions,
+    ) -> Option<IVec3>
+    where
+        S: Into<String>,
+    {
+        let footprint: Vec<IBox3> = footprint
+            .iter()
+            .filter(|b| !b.is_empty())
+            .copied()
+            .collect();
+        let offset = nudge_offsets(options.max_nudge).find(|offset| {
+            footprint.iter().all(|bounds| {
+                let mut check = *bounds;
+                check.translate(*offset);
+                check.min -= IVec3::new(options.margin, options.margin, 0);
+                check.max += IVec3::new(options.margin, options.margin, 0);
+                !self.intersects(&check)
+            })
+        })?;
+
+        let label = label.into();
+        for bounds in footprint {
+            let mut bounds = bounds;
+            bounds.translate(offset);
+            self.insert(bounds, label.clone());
+        }
+        Some(offset)
+    }
+}
+
+fn cells_of(bounds: &IBox3) -> impl Iterator<Item = (i32, i32)> {
+    let min = (
+        bounds.min.x.div_euclid(CELL_SIZE),
+        bounds.min.y.div_euclid(CELL_SIZE),
+    );
+    let max = (
+        bounds.max.x.div_euclid(CELL_SIZE),
+        bounds.max.y.div_euclid(CELL_SIZE),
+    );
+    (min.1..=max.1).flat_map(move |y| (min.0..=max.0).map(move |x| (x, y)))
+}
+
+/// X/Y offsets ordered by distance, starting with no offset.
+fn nudge_offsets(max_nudge: i32) -> impl Iterator<Item = IVec3> {
+    let mut offsets: Vec<IVec3> = (-max_nudge..=max_nudge)
+        .flat_map(|y| (-max_nudge..=max_nudge).map(move |x| IVec3::new(x, y, 0)))
+        .filter(|v| v.x * v.x + v.y * v.y <= max_nudge * max_nudge)
+        .collect();
+    offsets.sort_by_key(|v| (v.x * v.x + v.y * v.y, v.y, v.x));
+    offsets.into_iter()
+}
+
+impl Object {
+    /// World-space bounds of each VoxelSet in the object, including those
+    /// nested in groups.  Group members are positioned in world space, not
+    /// relative to the group.
+    pub fn footprint(&self) -> Vec<IBox3> {
+        match &self.imp {
+            ObjectImp::VoxelSet(_) => {
+                let mut bounds = self.oriented_voxel_set().unwrap().bounds();
+                if bounds.is_empty() {
+                    return vec![];
+                }
+                bounds.translate(self.position);
+                vec![bounds]
+            }
+            ObjectImp::Group(group) => group.objects.iter().flat_map(|o| o.footprint()).collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Moves the object, and any group members, by the offset.
+    pub fn translate(&mut self, offset: IVec3) {
+        self.position += offset;
+        if let ObjectImp::Group(group) = &mut self.imp {
+            for object in group.objects.iter_mut() {
+                object.translate(offset);
+            }
+        }
+    }
+}
+
+impl Scene2 {
+    /// Places the object in the scene's placement index, moving it if the
+    /// options allow a nudge.  Returns false, leaving the object unchanged,
+    /// if it cannot be placed without intersecting existing placements.
+    pub fn place_object(&mut self, object: &mut Object, options: &PlacementOptions) -> bool {
+        let footprint = object.footprint();
+        match self
+            .placements
+            .place(&footprint, object.generator_id.as_str(), options)
+        {
+            Some(offset) => {
+                object.translate(offset);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cube(min: (i32, i32, i32), size: i32) -> IBox3 {
+        let min = IVec3::new(min.0, min.1, min.2);
+        IBox3::from_min_max(min, min + IVec3::splat(size - 1))
+    }
+
+    #[test]
+    fn test_index_query() {
+        let mut index = PlacementIndex::new();
+        index.insert(cube((0, 0, 0), 4), "a");
+        index.insert(cube((40, -40, 0), 40), "b");
+        index.reserve(IVec2::new(-20, -20), IVec2::new(-18, -18), "road");
+
+        assert!(index.intersects(&cube((3, 3, 3), 2)));
+        assert!(!index.intersects(&cube((4, 4, 0), 2)));
+        assert!(!index.intersects(&cube((3, 3, 4), 2)));
+        assert_eq!(index.query(&cube((50, -10, 10), 1))[0].label, "b");
+        assert_eq!(index.query(&cube((-19, -19, 500), 1))[0].label, "road");
+        assert!(index.query(&cube((-60, 60, 0), 4)).is_empty());
+    }
+
+    #[test]
+    fn test_place_reject_and_nudge() {
+        let mut index = PlacementIndex::new();
+        index.insert(cube((0, 0, 0), 4), "a");
+
+        let options = PlacementOptions::default();
+        assert_eq!(index.place(&[cube((2, 2, 0), 4)], "b", &options), None);
+        assert_eq!(
+            index.place(&[cube((4, 0, 0), 4)], "b", &options),
+            Some(IVec3::ZERO)
+        );
+
+        // Blocked on the left by "a" and on the right by "b", so the
+        // nearest free spot is off to the side in Y
+        let options = options.with_max_nudge(4);
+        let offset = index.place(&[cube((2, 0, 0), 4)], "c", &options).unwrap();
+        assert_eq!(offset.x * offset.x + offset.y * offset.y, 16);
+        assert_eq!(index.len(), 3);
+
+        let options = PlacementOptions::default().with_margin(2);
+        assert_eq!(index.place(&[cube((9, 0, 0), 2)], "d", &options), None);
+
+        index.truncate(1);
+        assert_eq!(index.len(), 1);
+        assert!(!index.intersects(&cube((4, 0, 0), 4)));
+        assert!(index.intersects(&cube((0, 0, 0), 1)));
+    }
+
+    #[test]
+    fn test_place_object_group() {
+        let mut model = VoxelSet::new();
+        model.register_block(Block::color("stone", 80, 80, 80));
+        model.set_voxel((0, 0, 0), "stone");
+
+        let ctx = GenContext::new("stone", 1);
+        let mut scene = Scene2::new();
+        let mut first = ctx
+            .with_center(IVec3::new(8, 0, 0))
+            .to_object(model.clone());
+        assert!(scene.place_object(&mut first, &PlacementOptions::default()));
+
+        let mut group = Group::new();
+        group.push(&ctx.with_center(IVec3::new(0, 0, 0)), model.clone());
+        group.push(&ctx.with_center(IVec3::new(8, 0, 0)), model.clone());
+        let mut object = ctx.to_object(VoxelSet::new());
+        object.imp = ObjectImp::Group(Box::new(group));
+
+        assert!(!scene.place_object(&mut object, &PlacementOptions::default()));
+        let options = PlacementOptions::default().with_max_nudge(1);
+        assert!(scene.place_object(&mut object, &options));
+        assert_eq!(object.position, IVec3::new(0, -1, 0));
+        let ObjectImp::Group(group) = &object.imp else {
+            panic!("expected a group");
+        };
+        assert_eq!(group.objects[1].position, IVec3::new(8, -1, 0));
+    }
+
+    #[test]
+    fn test_cluster_places_groups() {
+        let mut scene = Scene2::new();
+        let ctx = GenContext::new("flower_field", 3);
+        let VoxelModel::Group(field) = generate_model(&ctx, &mut scene) else {
+            panic!("expected a group");
+        };
+
+        // Each flower cluster is recorded as a unit under its own generator,
+        // replacing the placements of its individual flowers
+        let recorded = scene
+            .placements
+            .iter()
+            .filter(|p| p.label == "flower_cluster")
+            .count();
+        assert!(recorded > 0);
+        assert_eq!(scene.placements.len(), recorded);
+        let boxes: usize = field.objects.iter().map(|o| o.footprint().len()).sum();
+        assert_eq!(boxes, recorded);
+    }
+}
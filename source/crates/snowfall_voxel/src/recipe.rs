@@ -0,0 +1,406 @@
+//----------------------------------------------------------------------------//
+//! Declarative generators composed from other generators.
+//!
+//! A recipe describes the same pipeline composite generators like
+//! `hill_with_road` implement in code:
+//!
+//! ```yaml
+//! description: Hill with roads, flowers, trees, and clouds
+//! terrain:
+//!   generator: hill4
+//! terrain_steps:
+//!   - generator: road
+//!     repeat: 4
+//! objects:
+//!   - generator: flower_field
+//!   - generator: cluster2
+//!     repeat: 4
+//!     params:
+//!       count: [120, 400]
+//!       range: 248
+//!       generators: [[10, tree1], [10, tree2], [80, pine_tree]]
+//!   - generator: cloud_cluster
+//! ```
+//!
+//! The `terrain` step's VoxelSet replaces the scene terrain.  `terrain_steps`
+//! run for their effect on the terrain (e.g. `road`, `rocks`) and their
+//! output is discarded.  The outputs of the `objects` steps are collected
+//! into the recipe's Group; clusters are `cluster2` steps with a weighted
+//! `generators` list.
+//!
+//! Every step draws its seeds, one per repetition, from an RNG seeded by the
+//! recipe's seed, in the order the steps are listed.
+//!
+//! Recipes are registered by file stem with
+//! `GeneratorRegistry::register_recipe_dir`, after which `try_generate_model`
+//! runs them like any other generator.  Recipes may use each other but not
+//! in a cycle, either directly or through a cluster step's `generators`.
+//!
+//----------------------------------------------------------------------------//
+
+use crate::generator::error_context;
+use crate::internal::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecipeStep {
+    pub generator: String,
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub params: serde_json::Value,
+    #[serde(default = "RecipeStep::default_repeat")]
+    pub repeat: u32,
+}
+
+impl RecipeStep {
+    fn default_repeat() -> u32 {
+        1
+    }
+
+    /// Names in the weighted `[weight, generator]` list of a cluster step's
+    /// `generators` param, with their index in the list.
+    fn weighted_generators(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.params
+            .get("generators")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .filter_map(|(i, entry)| Some((i, entry.get(1)?.as_str()?)))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Recipe {
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub terrain: Option<RecipeStep>,
+    #[serde(default)]
+    pub terrain_steps: Vec<RecipeStep>,
+    #[serde(default)]
+    pub objects: Vec<RecipeStep>,
+}
+
+impl Recipe {
+    pub fn from_yaml_str(s: &str) -> Result<Recipe, Error> {
+        serde_yaml::from_str(s).map_err(|e| Error::Generator(format!("invalid recipe: {}", e)))
+    }
+
+    pub fn read_file(path: &str) -> Result<Recipe, Error> {
+        let s = std::fs::read_to_string(path)?;
+        Self::from_yaml_str(&s).map_err(|e| error_context(e, path))
+    }
+
+    fn steps(&self) -> impl Iterator<Item = (String, &RecipeStep)> {
+        let terrain = self.terrain.iter().map(|s| ("terrain".to_string(), s));
+        let terrain_steps = self
+            .terrain_steps
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (format!("terrain_steps[{}]", i), s));
+        let objects = self
+            .objects
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (format!("objects[{}]", i), s));
+        terrain.chain(terrain_steps).chain(objects)
+    }
+
+    /// Names of the generators the recipe runs: each step's generator plus
+    /// those in the weighted `generators` list of cluster steps.
+    fn referenced_generators(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        for (_, step) in self.steps() {
+            names.push(step.generator.as_str());
+            names.extend(step.weighted_generators().map(|(_, name)| name));
+        }
+        names
+    }
+
+    /// Checks each step's generator and params against the registry,
+    /// including the generators a cluster step chooses from.
+    pub fn validate(&self, registry: &GeneratorRegistry) -> Result<(), Error> {
+        for (path, step) in self.steps() {
+            registry
+                .validate(&step.generator, &step.params)
+                .map_err(|e| error_context(e, &path))?;
+            for (i, name) in step.weighted_generators() {
+                if registry.get(name).is_none() {
+                    return Err(Error::Generator(format!(
+                        "{}.params.generators[{}]: unknown generator '{}'",
+                        path, i, name
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn generate(&self, ctx: &GenContext, scene: &mut Scene2) -> Result<Group, Error> {
+        let mut rng = ctx.make_rng();
+
+        if let Some(step) = &self.terrain {
+            for _ in 0..step.repeat {
+                let ctx = step_context(ctx, step, rng.seed8());
+                match try_generate_model(&ctx, scene)? {
+                    VoxelModel::VoxelSet(terrain) => scene.terrain = *terrain,
+                    _ => {
+                        return Err(Error::Generator(format!(
+                            "terrain: {}: terrain generators must return a VoxelSet",
+                            ctx.generator
+                        )))
+                    }
+                }
+            }
+        }
+
+        for step in self.terrain_steps.iter() {
+            for _ in 0..step.repeat {
+                let ctx = step_context(ctx, step, rng.seed8());
+                try_generate_model(&ctx, scene)?;
+            }
+        }
+
+        let mut group = Group::new();
+        for step in self.objects.iter() {
+            for _ in 0..step.repeat {
+                let ctx = step_context(ctx, step, rng.seed8());
+                let object = try_generate_object(&ctx, scene)?;
+                match object.imp {
+                    ObjectImp::Empty => {}
+                    ObjectImp::Group(g) => group.objects.extend(g.objects),
+                    _ => group.objects.push(object),
+                }
+            }
+        }
+        Ok(group)
+    }
+}
+
+fn step_context(ctx: &GenContext, step: &RecipeStep, seed: u64) -> GenContext {
+    ctx.fork(step.generator.clone(), seed)
+        .with_params(step.params.clone())
+}
+
+pub struct RecipeGenerator {
+    name: String,
+    recipe: Recipe,
+}
+
+impl RecipeGenerator {
+    pub fn new(name: &str, recipe: Recipe) -> Self {
+        Self {
+            name: name.to_string(),
+            recipe,
+        }
+    }
+}
+
+impl Generator for RecipeGenerator {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.recipe.description
+    }
+
+    fn version(&self) -> Option<u32> {
+        None
+    }
+
+    fn params_type(&self) -> &'static str {
+        "Recipe"
+    }
+
+    fn default_params(&self) -> serde_json::Value {
+        serde_json::Value::Null
+    }
+
+    fn params_schema(&self) -> ParamSchema {
+        ParamSchema::Object(vec![])
+    }
+
+    fn generate(&self, ctx: &GenContext, scene: &mut Scene2) -> Result<VoxelModel, Error> {
+        self.recipe
+            .generate(ctx, scene)
+            .map(Into::into)
+            .map_err(|e| error_context(e, &format!("recipe {}", self.name)))
+    }
+}
+
+impl GeneratorRegistry {
+    /// Registers every `.yaml` file in the directory as a recipe named by
+    /// its file stem, then validates the recipes' steps.  Recipes may use
+    /// each other regardless of file order, but a cycle is an error.
+    pub fn register_recipe_dir<P>(self, dir: P) -> Result<Self, Error>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let mut paths: Vec<_> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "yaml"))
+            .collect();
+        paths.sort();
+
+        let mut registry = self;
+        let mut recipes = Vec::new();
+        for path in paths {
+            let name = path.file_stem().unwrap().to_string_lossy().to_string();
+            let recipe = Recipe::read_file(&path.to_string_lossy())?;
+            registry = registry.register(RecipeGenerator::new(&name, recipe.clone()));
+            recipes.push((name, recipe));
+        }
+
+        for (name, recipe) in recipes.iter() {
+            recipe
+                .validate(&registry)
+                .map_err(|e| error_context(e, &format!("recipe {}", name)))?;
+        }
+
+        let recipes: HashMap<String, Recipe> = recipes.into_iter().collect();
+        let mut checked = Vec::new();
+        for name in recipes.keys() {
+            check_recipe_cycles(name, &recipes, &mut Vec::new(), &mut checked)?;
+        }
+        Ok(registry)
+    }
+}
+
+/// Walks the recipes `name` uses, returning an error naming the cycle if one
+/// leads back to a recipe already on `path`.
+fn check_recipe_cycles<'a>(
+    name: &'a str,
+    recipes: &'a HashMap<String, Recipe>,
+    path: &mut Vec<&'a str>,
+    checked: &mut Vec<&'a str>,
+) -> Result<(), Error> {
+    if let Some(start) = path.iter().position(|n| *n == name) {
+        let mut cycle = path[start..].to_vec();
+        cycle.push(name);
+        return Err(Error::Generator(format!(
+            "recipe {}: recipes form a cycle: {}",
+            cycle[0],
+            cycle.join(" -> ")
+        )));
+    }
+    if checked.contains(&name) {
+        return Ok(());
+    }
+    let Some(recipe) = recipes.get(name) else {
+        return Ok(());
+    };
+
+    path.push(name);
+    for generator in recipe.referenced_generators() {
+        check_recipe_cycles(generator, recipes, path, checked)?;
+    }
+    path.pop();
+    checked.push(name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+
+    const FLOWER_FIELD: &str = r#"
+description: Same pipeline as the flower_field generator
+objects:
+  - generator: cluster2
+    params:
+      count: [30, 40]
+      range: 100
+      closest_distance: 16.0
+      generators: [[10, flower_cluster]]
+"#;
+
+    #[test]
+    fn test_recipe_matches_code() {
+        let registry = GeneratorRegistry::with_builtins().register(RecipeGenerator::new(
+            "flower_field_recipe",
+            Recipe::from_yaml_str(FLOWER_FIELD).unwrap(),
+        ));
+        let ctx = GenContext::new("flower_field_recipe", 17).with_registry(Arc::new(registry));
+        let VoxelModel::Group(recipe) = generate_model(&ctx, &mut Scene2::new()) else {
+            panic!("expected a group");
+        };
+        let code =
+            generators::flower_field(&ctx.fork("flower_field", 17), &mut Scene2::new()).unwrap();
+
+        let positions = |g: &Group| g.objects.iter().map(|o| o.position).collect::<Vec<_>>();
+        assert!(!recipe.objects.is_empty());
+        assert_eq!(positions(&recipe), positions(&code));
+    }
+
+    #[test]
+    fn test_recipe_errors() {
+        let err = Recipe::from_yaml_str("objects:\n  - generator: tree1\n    parmas: {}\n");
+        assert!(matches!(err, Err(Error::Generator(_))));
+
+        let recipe = Recipe::from_yaml_str(
+            "objects:\n  - generator: cluster2\n    params: { closest_distanse: 2 }\n",
+        )
+        .unwrap();
+        let err = recipe.validate(&GeneratorRegistry::builtin()).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("objects[0]: cluster2: params.closest_distanse"));
+
+        let recipe = Recipe::from_yaml_str(
+            "objects:\n  - generator: cluster2\n    params: { generators: [[10, tree1], [10, treee]] }\n",
+        )
+        .unwrap();
+        let err = recipe.validate(&GeneratorRegistry::builtin()).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("objects[0].params.generators[1]: unknown generator 'treee'"));
+    }
+
+    #[test]
+    fn test_recipe_cycles() {
+        let dir =
+            std::env::temp_dir().join(format!("snowfall_recipe_cycles_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("a.yaml"),
+            "objects:\n  - generator: cluster2\n    params: { generators: [[1, b]] }\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("b.yaml"), "objects:\n  - generator: a\n").unwrap();
+
+        let result = GeneratorRegistry::with_builtins().register_recipe_dir(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let Err(err) = result else {
+            panic!("expected a cycle error");
+        };
+        assert!(err.to_string().contains("a -> b -> a") || err.to_string().contains("b -> a -> b"));
+    }
+
+    #[test]
+    fn test_recipe_terrain_must_be_voxel_set() {
+        let registry = GeneratorRegistry::with_builtins().register(RecipeGenerator::new(
+            "bad_terrain",
+            Recipe::from_yaml_str("terrain:\n  generator: flower_cluster\n").unwrap(),
+        ));
+        let ctx = GenContext::new("bad_terrain", 1).with_registry(Arc::new(registry));
+        let Err(err) = try_generate_model(&ctx, &mut Scene2::new()) else {
+            panic!("expected an error");
+        };
+        assert!(err
+            .to_string()
+            .contains("recipe bad_terrain: terrain: flower_cluster"));
+    }
+
+    #[test]
+    fn test_content_recipes() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../tools/voxel_viewer/content/recipes");
+        let registry = GeneratorRegistry::with_builtins()
+            .register_recipe_dir(dir)
+            .unwrap();
+        assert!(registry.get("meadow").is_some());
+    }
+}
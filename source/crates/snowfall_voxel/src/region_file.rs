@@ -0,0 +1,521 @@
+//----------------------------------------------------------------------------//
+//! Random-access container for large VoxelSets.
+//!
+//! The set is split into cubic regions that are compressed independently so
+//! readers can load just the regions intersecting a box.  The file layout is:
+//!
+//! ```text
+//! header   magic (8) | version (4) | region size (4) | codec (1) | level (1)
+//!          | reserved (6) | index offset (8) | index length (8)
+//! blobs    compressed palette/attributes/metadata and region data, in any
+//!          order
+//! index    compressed RegionIndex locating every blob
+//! ```
+//!
+//! Updates are append-only: new blobs and a new index are written at the end
+//! of the file and the header is rewritten last to point at the new index.
+//! Replaced blobs are left in place as dead space until the file is next
+//! written in full with `VoxelSet::write_region_file`.
+//!
+//----------------------------------------------------------------------------//
+
+use crate::internal::*;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+const REGION_FILE_IDENTIFIER: [u8; 8] = *b"SNVREG\0\0";
+const REGION_FILE_VERSION: u32 = 3;
+const HEADER_SIZE: u64 = 40;
+
+/// Version 1 had no codec and was always deflate compressed
+const HEADER_SIZE_V1: u64 = 32;
+
+/// Edge length of a region in voxels
+pub const REGION_SIZE: i32 = 32;
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct BlobRef {
+    offset: u64,
+    length: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RegionIndex {
+    meta: BlobRef,
+    regions: Vec<([i32; 3], BlobRef)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RegionMeta {
+    palette: VoxelPalette,
+    attributes: Vec<VoxelSetAttribute>,
+    metadata: VoxelSetMetadata,
+}
+
+/// Versions 1 and 2 did not store the set's metadata
+#[derive(Serialize, Deserialize)]
+struct RegionMetaV2 {
+    palette: VoxelPalette,
+    attributes: Vec<VoxelSetAttribute>,
+}
+
+/// Voxels of one region in world coordinates, including explicitly empty
+/// voxels.
+#[derive(Serialize, Deserialize, Default)]
+struct RegionData {
+    voxels: Vec<([i32; 3], PaletteIndex)>,
+}
+
+/// Returns the coordinate of the region containing the voxel.
+pub fn region_coord(p: IVec3) -> IVec3 {
+    p.div_euclid(IVec3::splat(REGION_SIZE))
+}
+
+/// Returns the inclusive voxel bounds of a region.
+pub fn region_bounds(region: IVec3) -> IBox3 {
+    let min = region * REGION_SIZE;
+    IBox3::from_min_max(min, min + IVec3::splat(REGION_SIZE - 1))
+}
+
+pub struct RegionFile {
+    file: File,
+    /// Files are updated in the layout they were created with
+    version: u32,
+    compression: Compression,
+    meta: RegionMeta,
+    meta_blob: BlobRef,
+    blobs: HashMap<IVec3, BlobRef>,
+}
+
+impl RegionFile {
+    /// Writes the full set as a new region file, replacing any existing file.
+    pub fn create(path: &str, voxel_set: &VoxelSet) -> Result<RegionFile, Error> {
+        Self::create_with(path, voxel_set, &Compression::default())
+    }
+
+    /// Like `create`.  Later updates to the file use the same compression.
+    pub fn create_with(
+        path: &str,
+        voxel_set: &VoxelSet,
+        compression: &Compression,
+    ) -> Result<RegionFile, Error> {
+        let mut regions: HashMap<IVec3, RegionData> = HashMap::new();
+        for (p, index) in all_voxels(voxel_set) {
+            regions
+                .entry(region_coord(p))
+                .or_default()
+                .voxels
+                .push((p.into(), index));
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        let mut region_file = RegionFile {
+            file,
+            version: REGION_FILE_VERSION,
+            // The header stores the level in a byte, and levels above 9
+            // compress the same as 9
+            compression: Compression {
+                level: compression.level.min(9),
+                ..*compression
+            },
+            meta: RegionMeta {
+                palette: voxel_set.palette.clone(),
+                attributes: voxel_set.attributes.clone(),
+                metadata: voxel_set.metadata.clone(),
+            },
+            meta_blob: BlobRef {
+                offset: 0,
+                length: 0,
+            },
+            blobs: HashMap::new(),
+        };
+
+        // Sort so identical sets produce identical files
+        let mut coords: Vec<IVec3> = regions.keys().copied().collect();
+        coords.sort_by_key(|c| (c.z, c.y, c.x));
+
+        region_file.file.write_all(&[0; HEADER_SIZE as usize])?;
+        for coord in coords {
+            let mut data = regions.remove(&coord).unwrap();
+            data.voxels.sort_by_key(|(p, _)| (p[2], p[1], p[0]));
+            let blob = region_file.append(&serialize_and_compress_with(
+                &data,
+                &region_file.compression,
+            )?)?;
+            region_file.blobs.insert(coord, blob);
+        }
+        region_file.commit(true)?;
+        Ok(region_file)
+    }
+
+    pub fn open(path: &str) -> Result<RegionFile, Error> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+
+        let mut header = [0u8; HEADER_SIZE as usize];
+        file.read_exact(&mut header[..HEADER_SIZE_V1 as usize])
+            .map_err(|_| Error::CorruptData("truncated region file header".to_string()))?;
+        if header[0..8] != REGION_FILE_IDENTIFIER {
+            return Err(Error::FileHeader(
+                String::from_utf8_lossy(&header[0..8]).to_string(),
+            ));
+        }
+        let region_size = u32::from_le_bytes(header[12..16].try_into().unwrap());
+        if region_size != REGION_SIZE as u32 {
+            return Err(Error::CorruptData(format!(
+                "unsupported region size {}",
+                region_size
+            )));
+        }
+
+        let version = u32::from_le_bytes(header[8..12].try_into().unwrap());
+        let (compression, index_ref) = match version {
+            1 => (Compression::default(), read_blob_ref(&header[16..32])),
+            2 | REGION_FILE_VERSION => {
+                file.read_exact(&mut header[HEADER_SIZE_V1 as usize..])
+                    .map_err(|_| Error::CorruptData("truncated region file header".to_string()))?;
+                let compression = Compression {
+                    codec: Codec::from_id(header[16])?,
+                    level: header[17] as u32,
+                };
+                (compression, read_blob_ref(&header[24..40]))
+            }
+            _ => return Err(Error::FileVersion(version.to_string())),
+        };
+
+        let mut region_file = RegionFile {
+            file,
+            version,
+            compression,
+            meta: RegionMeta {
+                palette: VoxelPalette::new(),
+                attributes: Vec::new(),
+                metadata: VoxelSetMetadata::default(),
+            },
+            meta_blob: BlobRef {
+                offset: 0,
+                length: 0,
+            },
+            blobs: HashMap::new(),
+        };
+        let codec = region_file.compression.codec;
+        let index: RegionIndex =
+            decompress_and_deserialize_with(&region_file.read_blob(index_ref)?, codec)?;
+        let meta_bytes = region_file.read_blob(index.meta)?;
+        region_file.meta = if version < REGION_FILE_VERSION {
+            let old: RegionMetaV2 = decompress_and_deserialize_with(&meta_bytes, codec)?;
+            RegionMeta {
+                palette: old.palette,
+                attributes: old.attributes,
+                metadata: VoxelSetMetadata::default(),
+            }
+        } else {
+            decompress_and_deserialize_with(&meta_bytes, codec)?
+        };
+        region_file.meta_blob = index.meta;
+        region_file.blobs = index
+            .regions
+            .into_iter()
+            .map(|(c, blob)| (IVec3::from(c), blob))
+            .collect();
+        Ok(region_file)
+    }
+
+    pub fn palette(&self) -> &VoxelPalette {
+        &self.meta.palette
+    }
+
+    /// Coordinates of all non-empty regions in the file.
+    pub fn regions(&self) -> Vec<IVec3> {
+        let mut coords: Vec<IVec3> = self.blobs.keys().copied().collect();
+        coords.sort_by_key(|c| (c.z, c.y, c.x));
+        coords
+    }
+
+    /// Loads only the regions intersecting `bounds` and returns the voxels
+    /// inside `bounds`.
+    pub fn read_box(&mut self, bounds: &IBox3) -> Result<VoxelSet, Error> {
+        let mut voxel_set = self.empty_set();
+        for coord in self.regions() {
+            if !region_bounds(coord).intersects(bounds) {
+                continue;
+            }
+            for (p, index) in self.read_region_data(coord)?.voxels {
+                let p = IVec3::from(p);
+                if bounds.contains(p) {
+                    voxel_set.set(p, index);
+                }
+            }
+        }
+        Ok(voxel_set)
+    }
+
+    pub fn read_all(&mut self) -> Result<VoxelSet, Error> {
+        let mut voxel_set = self.empty_set();
+        for coord in self.regions() {
+            for (p, index) in self.read_region_data(coord)?.voxels {
+                voxel_set.set(IVec3::from(p), index);
+            }
+        }
+        Ok(voxel_set)
+    }
+
+    /// Replaces the contents of one region with the voxels of `source` that
+    /// fall inside it.  Blocks in `source` are merged into the file palette.
+    pub fn write_region(&mut self, region: IVec3, source: &VoxelSet) -> Result<(), Error> {
+        let palette_len = self.meta.palette.iter().count();
+        let mut remap: HashMap<PaletteIndex, PaletteIndex> = HashMap::new();
+        for (index, block) in source.palette.iter() {
+            remap.insert(index, self.meta.palette.merge_block(block));
+        }
+        let palette_changed = self.meta.palette.iter().count() != palette_len;
+
+        let bounds = region_bounds(region);
+        let mut data = RegionData::default();
+        for (p, index) in all_voxels(source) {
+            if bounds.contains(p) {
+                data.voxels.push((p.into(), remap[&index]));
+            }
+        }
+        data.voxels.sort_by_key(|(p, _)| (p[2], p[1], p[0]));
+
+        if data.voxels.is_empty() {
+            self.blobs.remove(&region);
+        } else {
+            let blob = self.append(&serialize_and_compress_with(&data, &self.compression)?)?;
+            self.blobs.insert(region, blob);
+        }
+        self.commit(palette_changed)
+    }
+
+    // ------------------------------------------------------------------------
+    // Internals
+    // ------------------------------------------------------------------------
+
+    fn empty_set(&self) -> VoxelSet {
+        let mut voxel_set = VoxelSet::new();
+        voxel_set.palette = self.meta.palette.clone();
+        voxel_set.attributes = self.meta.attributes.clone();
+        voxel_set.metadata = self.meta.metadata.clone();
+        voxel_set
+    }
+
+    fn read_region_data(&mut self, coord: IVec3) -> Result<RegionData, Error> {
+        let blob = self.blobs[&coord];
+        decompress_and_deserialize_with(&self.read_blob(blob)?, self.compression.codec)
+    }
+
+    fn read_blob(&mut self, blob: BlobRef) -> Result<Vec<u8>, Error> {
+        // The offset and length come from the file itself, so check them
+        // before allocating
+        let file_length = self.file.metadata()?.len();
+        match blob.offset.checked_add(blob.length) {
+            Some(end) if end <= file_length => {}
+            _ => return Err(Error::CorruptData("region blob out of range".to_string())),
+        }
+        let mut bytes = vec![0; blob.length as usize];
+        self.file.seek(SeekFrom::Start(blob.offset))?;
+        self.file
+            .read_exact(&mut bytes)
+            .map_err(|_| Error::CorruptData("region blob out of range".to_string()))?;
+        Ok(bytes)
+    }
+
+    fn append(&mut self, bytes: &[u8]) -> Result<BlobRef, Error> {
+        let offset = self.file.seek(SeekFrom::End(0))?;
+        self.file.write_all(bytes)?;
+        Ok(BlobRef {
+            offset,
+            length: bytes.len() as u64,
+        })
+    }
+
+    /// Writes the index (and the palette if it changed) and then points the
+    /// header at it.
+    fn commit(&mut self, write_meta: bool) -> Result<(), Error> {
+        if write_meta {
+            // Older files keep their meta layout along with their header
+            let bytes = if self.version < REGION_FILE_VERSION {
+                serialize_and_compress_with(
+                    &RegionMetaV2 {
+                        palette: self.meta.palette.clone(),
+                        attributes: self.meta.attributes.clone(),
+                    },
+                    &self.compression,
+                )?
+            } else {
+                serialize_and_compress_with(&self.meta, &self.compression)?
+            };
+            self.meta_blob = self.append(&bytes)?;
+        }
+        let index = RegionIndex {
+            meta: self.meta_blob,
+            regions: self
+                .regions()
+                .into_iter()
+                .map(|c| (c.into(), self.blobs[&c]))
+                .collect(),
+        };
+        let index_ref = self.append(&serialize_and_compress_with(&index, &self.compression)?)?;
+
+        let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+        header.extend_from_slice(&REGION_FILE_IDENTIFIER);
+        header.extend_from_slice(&self.version.to_le_bytes());
+        header.extend_from_slice(&(REGION_SIZE as u32).to_le_bytes());
+        if self.version != 1 {
+            header.push(self.compression.codec.id());
+            header.push(self.compression.level as u8);
+            header.extend_from_slice(&[0; 6]);
+        }
+        header.extend_from_slice(&index_ref.offset.to_le_bytes());
+        header.extend_from_slice(&index_ref.length.to_le_bytes());
+        self.file.flush()?;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&header)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+fn read_blob_ref(bytes: &[u8]) -> BlobRef {
+    BlobRef {
+        offset: u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+        length: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+    }
+}
+
+fn all_voxels(voxel_set: &VoxelSet) -> impl Iterator<Item = (IVec3, PaletteIndex)> + '_ {
+    voxel_set.index_iter().chain(
+        voxel_set
+            .explicit_empty_iter()
+            .map(|p| (p, PaletteIndex::zero())),
+    )
+}
+
+impl VoxelSet {
+    pub fn write_region_file(&self, path: &str) -> Result<(), Error> {
+        RegionFile::create(path, self)?;
+        Ok(())
+    }
+
+    /// Reads the voxels within `bounds`, or the whole set if `bounds` is
+    /// `None`.
+    pub fn read_region_file(path: &str, bounds: Option<&IBox3>) -> Result<VoxelSet, Error> {
+        let mut region_file = RegionFile::open(path)?;
+        match bounds {
+            Some(bounds) => region_file.read_box(bounds),
+            None => region_file.read_all(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Each test gets its own directory so tests (and concurrent test runs)
+    /// don't write over each other's files.
+    fn temp_path(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!(
+            "snowfall_region_file_{}_{}",
+            std::process::id(),
+            name.replace('.', "_")
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name).to_string_lossy().to_string()
+    }
+
+    fn sample_set() -> VoxelSet {
+        let mut model = VoxelSet::new();
+        model.register_block(Block::color("stone", 80, 80, 80));
+        model.fill_box((-40, -3, 0), (40, 3, 2), "stone");
+        model.set_anchor("entrance", IVec3::new(0, -3, 0));
+        model
+    }
+
+    #[test]
+    fn test_region_file_read_box() {
+        let path = temp_path("read_box.vreg");
+        let model = sample_set();
+        model.write_region_file(&path).unwrap();
+
+        let all = VoxelSet::read_region_file(&path, None).unwrap();
+        assert_eq!(all.voxel_iter(false).len(), model.voxel_iter(false).len());
+        assert_eq!(all.anchor("entrance"), Some(IVec3::new(0, -3, 0)));
+
+        let bounds = IBox3::from_min_max(IVec3::new(0, 0, 0), IVec3::new(9, 9, 9));
+        let part = VoxelSet::read_region_file(&path, Some(&bounds)).unwrap();
+        assert_eq!(part.voxel_iter(false).len(), 10 * 4 * 3);
+        assert_eq!(part.get_voxel((-1, 0, 0)).id, "empty");
+        assert_eq!(part.get_voxel((5, 0, 0)).id, "stone");
+    }
+
+    #[test]
+    fn test_region_file_update_in_place() {
+        let path = temp_path("update.vreg");
+        let mut region_file =
+            RegionFile::create_with(&path, &sample_set(), &Compression::fast()).unwrap();
+        let before = region_file.regions();
+
+        let mut patch = VoxelSet::new();
+        patch.register_block(Block::color("gold", 200, 180, 0));
+        patch.set((1, 1, 5), "gold");
+        region_file.write_region(IVec3::ZERO, &patch).unwrap();
+        drop(region_file);
+
+        let mut region_file = RegionFile::open(&path).unwrap();
+        assert_eq!(region_file.regions(), before);
+        let model = region_file.read_all().unwrap();
+        assert_eq!(model.get_voxel((1, 1, 5)).id, "gold");
+        assert_eq!(model.get_voxel((1, 1, 0)).id, "empty");
+        assert_eq!(model.get_voxel((-1, 1, 0)).id, "stone");
+        assert_eq!(model.get_voxel((33, 1, 0)).id, "stone");
+    }
+
+    #[test]
+    fn test_region_file_rejects_out_of_range_index() {
+        let path = temp_path("corrupt.vreg");
+        sample_set().write_region_file(&path).unwrap();
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[32..40].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(&path, bytes).unwrap();
+        assert!(matches!(
+            RegionFile::open(&path),
+            Err(Error::CorruptData(_))
+        ));
+    }
+
+    #[test]
+    fn test_region_file_reads_version_2() {
+        let path = temp_path("v2.vreg");
+        let mut region_file = RegionFile::create(&path, &sample_set()).unwrap();
+        region_file.version = 2;
+        region_file.commit(true).unwrap();
+        drop(region_file);
+
+        let mut region_file = RegionFile::open(&path).unwrap();
+        assert_eq!(region_file.version, 2);
+        let model = region_file.read_all().unwrap();
+        assert_eq!(model.get_voxel((0, 0, 0)).id, "stone");
+        assert_eq!(model.anchor("entrance"), None);
+    }
+
+    #[test]
+    fn test_region_file_clamps_level() {
+        let path = temp_path("level.vreg");
+        let compression = Compression {
+            codec: Codec::Deflate,
+            level: 300,
+        };
+        RegionFile::create_with(&path, &sample_set(), &compression).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(bytes[17], 9);
+        assert!(RegionFile::open(&path).unwrap().read_all().is_ok());
+    }
+}
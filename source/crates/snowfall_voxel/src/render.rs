@@ -0,0 +1,231 @@
+//----------------------------------------------------------------------------//
+//! Headless software renderer for generating thumbnails of voxel models.
+//!
+//! Rasterizes the same triangle lists used by the runtime renderer with a
+//! simple z-buffer and flat directional shading.  The output is fully
+//! deterministic, so it can be used for image comparisons in tests.
+//!
+//----------------------------------------------------------------------------//
+
+use crate::internal::*;
+use image::{Rgba, RgbaImage};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// Standard isometric view looking down at the model from the -X, -Y
+    /// corner.
+    Isometric,
+
+    /// Orthographic view with the camera direction given by an azimuth around
+    /// the Z axis and an elevation above the XY plane, both in radians.
+    Orthographic { azimuth: f32, elevation: f32 },
+}
+
+impl Projection {
+    fn angles(&self) -> (f32, f32) {
+        match *self {
+            Projection::Isometric => (
+                -3.0 * std::f32::consts::FRAC_PI_4,
+                (1.0 / 2.0f32.sqrt()).atan(),
+            ),
+            Projection::Orthographic { azimuth, elevation } => (azimuth, elevation),
+        }
+    }
+}
+
+pub struct RenderOptions {
+    pub width: u32,
+    pub height: u32,
+    pub projection: Projection,
+
+    /// Direction *towards* the light
+    pub light_direction: Vec3,
+    pub ambient: f32,
+    pub background: [u8; 4],
+
+    /// Fraction of the image left empty around the model
+    pub margin: f32,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            width: 256,
+            height: 256,
+            projection: Projection::Isometric,
+            light_direction: Vec3::new(-0.4, -0.6, 1.0),
+            ambient: 0.35,
+            background: [0, 0, 0, 0],
+            margin: 0.05,
+        }
+    }
+}
+
+impl RenderOptions {
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    pub fn with_background(mut self, background: [u8; 4]) -> Self {
+        self.background = background;
+        self
+    }
+}
+
+pub fn render_voxel_set(voxel_set: &VoxelSet, options: &RenderOptions) -> RgbaImage {
+    let instance = MeshInstance::from_voxel_set("model", voxel_set, Vec3::ZERO);
+    render_instances(&[instance], options)
+}
+
+pub fn render_scene(scene: &Scene2, options: &RenderOptions) -> RgbaImage {
+    render_instances(&scene_mesh_instances(scene), options)
+}
+
+/// Renders the instances, scaling the view so the combined geometry fits
+/// the image.
+pub fn render_instances(instances: &[MeshInstance], options: &RenderOptions) -> RgbaImage {
+    let mut image = RgbaImage::from_pixel(options.width, options.height, Rgba(options.background));
+
+    // Camera basis: `forward` points from the camera into the scene
+    let (azimuth, elevation) = options.projection.angles();
+    let eye = Vec3::new(
+        elevation.cos() * azimuth.cos(),
+        elevation.cos() * azimuth.sin(),
+        elevation.sin(),
+    );
+    let forward = -eye;
+    let mut right = forward.cross(Vec3::Z);
+    if right.length_squared() < 1e-6 {
+        right = Vec3::X;
+    }
+    let right = right.normalize();
+    let up = right.cross(forward).normalize();
+    let light = options.light_direction.normalize_or_zero();
+
+    // Transform everything into view space: (right, up, depth)
+    let mut triangles: Vec<([Vec3; 3], [f32; 4])> = Vec::new();
+    for instance in instances {
+        let mesh = &instance.mesh;
+        for i in (0..mesh.positions.len()).step_by(3) {
+            let normal = Vec3::from(instance.transform_normal(mesh.normals[i]));
+            let shade = options.ambient + (1.0 - options.ambient) * normal.dot(light).max(0.0);
+            let c = mesh.colors[i];
+            let color = [c[0] * shade, c[1] * shade, c[2] * shade, c[3]];
+
+            let mut verts = [Vec3::ZERO; 3];
+            for (j, v) in verts.iter_mut().enumerate() {
+                let p = Vec3::from(instance.transform_point(mesh.positions[i + j]));
+                *v = Vec3::new(p.dot(right), p.dot(up), p.dot(forward));
+            }
+            triangles.push((verts, color));
+        }
+    }
+    if triangles.is_empty() {
+        return image;
+    }
+
+    // Fit the projected bounds to the image
+    let mut min = Vec2::splat(f32::MAX);
+    let mut max = Vec2::splat(f32::MIN);
+    for (verts, _) in triangles.iter() {
+        for v in verts {
+            min = min.min(v.truncate());
+            max = max.max(v.truncate());
+        }
+    }
+    let (w, h) = (options.width as f32, options.height as f32);
+    let extent = (max - min).max(Vec2::splat(1e-3));
+    let usable = 1.0 - 2.0 * options.margin;
+    let scale = (w * usable / extent.x).min(h * usable / extent.y);
+    let center = (min + max) * 0.5;
+    let to_screen = |v: Vec3| -> Vec3 {
+        Vec3::new(
+            w * 0.5 + (v.x - center.x) * scale,
+            h * 0.5 - (v.y - center.y) * scale,
+            v.z,
+        )
+    };
+
+    let mut depth = vec![f32::MAX; (options.width * options.height) as usize];
+    for (verts, color) in triangles.iter() {
+        let [a, b, c] = verts.map(to_screen);
+        rasterize_triangle(&mut image, &mut depth, a, b, c, *color);
+    }
+    image
+}
+
+fn edge(a: Vec3, b: Vec3, p: Vec2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+fn rasterize_triangle(
+    image: &mut RgbaImage,
+    depth: &mut [f32],
+    a: Vec3,
+    b: Vec3,
+    c: Vec3,
+    color: [f32; 4],
+) {
+    let area = edge(a, b, c.truncate());
+    if area.abs() < 1e-8 {
+        return;
+    }
+
+    let (width, height) = (image.width() as i32, image.height() as i32);
+    let x0 = a.x.min(b.x).min(c.x).floor().max(0.0) as i32;
+    let y0 = a.y.min(b.y).min(c.y).floor().max(0.0) as i32;
+    let x1 = (a.x.max(b.x).max(c.x).ceil() as i32).min(width - 1);
+    let y1 = (a.y.max(b.y).max(c.y).ceil() as i32).min(height - 1);
+
+    let pixel = Rgba(color.map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8));
+    for y in y0..=y1 {
+        for x in x0..=x1 {
+            // Sample at the pixel center; accept either winding
+            let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let w0 = edge(b, c, p) / area;
+            let w1 = edge(c, a, p) / area;
+            let w2 = edge(a, b, p) / area;
+            if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                continue;
+            }
+            let z = w0 * a.z + w1 * b.z + w2 * c.z;
+            let index = (y * width + x) as usize;
+            if z < depth[index] {
+                depth[index] = z;
+                image.put_pixel(x as u32, y as u32, pixel);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render_cube() {
+        let mut model = VoxelSet::new();
+        model.register_block(Block::color("red", 255, 0, 0));
+        model.fill_box((0, 0, 0), (3, 3, 3), "red");
+
+        let options = RenderOptions::default().with_size(64, 64);
+        let image = render_voxel_set(&model, &options);
+        assert_eq!(image.dimensions(), (64, 64));
+
+        let center = image.get_pixel(32, 32);
+        assert_eq!(center[3], 255);
+        assert!(center[0] > 0 && center[1] == 0);
+        assert_eq!(*image.get_pixel(0, 0), Rgba(options.background));
+        assert_eq!(*image.get_pixel(63, 63), Rgba(options.background));
+
+        let again = render_voxel_set(&model, &options);
+        assert_eq!(image.as_raw(), again.as_raw());
+    }
+}
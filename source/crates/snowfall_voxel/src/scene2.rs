@@ -1,8 +1,15 @@
 use crate::internal::*;
+use std::borrow::Cow;
 
 pub struct Scene2 {
     pub terrain: VoxelSet, // Eventually EditableVoxel
     pub root: Object,
+
+    /// Footprints of the objects placed so far, used to avoid overlaps
+    pub placements: PlacementIndex,
+
+    /// Biomes that generators filling the scene should follow, if any
+    pub biomes: Option<BiomeMap>,
 }
 
 impl Scene2 {
@@ -14,10 +21,58 @@ impl Scene2 {
                 seed: 0,
                 params: serde_json::Value::Null,
                 position: IVec3::ZERO.clone(),
+                orientation: Orientation::default(),
                 imp: ObjectImp::Empty,
             },
+            placements: PlacementIndex::new(),
+            biomes: None,
         }
     }
+
+    /// Creates a new scene with the output of the generator as the root.
+    pub fn generate(ctx: &GenContext) -> Self {
+        let mut scene = Self::new();
+        scene.root = generate_object(ctx, &mut scene);
+        scene
+    }
+}
+
+/// Runs the generator and converts the resulting model into an Object,
+/// recursively generating any models referenced by a VoxelScene.  Panics if
+/// a generator fails; see try_generate_object.
+pub fn generate_object(ctx: &GenContext, scene: &mut Scene2) -> Object {
+    try_generate_object(ctx, scene).unwrap_or_else(|e| panic!("{}", e))
+}
+
+pub fn try_generate_object(ctx: &GenContext, scene: &mut Scene2) -> Result<Object, Error> {
+    let imp = match try_generate_model(ctx, scene)? {
+        VoxelModel::Empty => ObjectImp::Empty,
+        VoxelModel::VoxelSet(voxel_set) => ObjectImp::VoxelSet(voxel_set),
+        VoxelModel::Group(group) => ObjectImp::Group(group),
+        VoxelModel::VoxelScene(voxel_scene) => {
+            let mut group = Group::new();
+            for layer in voxel_scene.layers.iter() {
+                for model_ref in layer.models.iter() {
+                    let ctx = ctx
+                        .fork(model_ref.model_id.clone(), model_ref.seed)
+                        .with_center(model_ref.position)
+                        .with_params(model_ref.params.clone());
+                    let mut object = try_generate_object(&ctx, scene)?;
+                    object.orientation = model_ref.orientation;
+                    group.objects.push(object);
+                }
+            }
+            ObjectImp::Group(Box::new(group))
+        }
+    };
+    Ok(Object {
+        generator_id: ctx.generator.clone(),
+        seed: ctx.seed,
+        params: ctx.params.clone(),
+        position: ctx.center,
+        orientation: Orientation::default(),
+        imp,
+    })
 }
 
 pub struct Object {
@@ -25,9 +80,28 @@ pub struct Object {
     pub seed: u64,
     pub params: serde_json::Value,
     pub position: IVec3,
+
+    /// Orientation applied to the object's voxels when it is placed in the
+    /// world.  The position is not affected.
+    pub orientation: Orientation,
     pub imp: ObjectImp,
 }
 
+impl Object {
+    /// Returns the object's VoxelSet with the orientation applied, or None
+    /// if the object is not a VoxelSet.
+    pub fn oriented_voxel_set(&self) -> Option<Cow<'_, VoxelSet>> {
+        let ObjectImp::VoxelSet(voxel_set) = &self.imp else {
+            return None;
+        };
+        if self.orientation.is_identity() {
+            Some(Cow::Borrowed(voxel_set.as_ref()))
+        } else {
+            Some(Cow::Owned(voxel_set.oriented(&self.orientation)))
+        }
+    }
+}
+
 pub trait IntoObjectImp {
     fn into_object_imp(self, ctx: &GenContext) -> ObjectImp;
 }
@@ -56,6 +130,7 @@ impl Group {
             seed: ctx.seed,
             position: ctx.center,
             params: ctx.params.clone(),
+            orientation: Orientation::default(),
             imp: model.into_object_imp(ctx),
         });
     }
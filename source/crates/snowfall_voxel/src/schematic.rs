@@ -0,0 +1,286 @@
+//----------------------------------------------------------------------------//
+//! Human-readable YAML schematics for small hand-authored models.
+//!
+//! A schematic is a block legend plus one character grid per Z layer:
+//!
+//! ```yaml
+//! blocks:
+//!   g: { id: green, color: [40, 90, 20] }
+//!   f: { id: flower, color: [160, 5, 40] }
+//! origin: [-1, -1, 0]
+//! layers:
+//!   - |
+//!     ...
+//!     .g.
+//!     ...
+//!   - |
+//!     .g.
+//!     gfg
+//!     .g.
+//! ```
+//!
+//! Layers are listed bottom to top.  Within a layer the first row is the
+//! largest Y and the first column the smallest X.  `origin` is the voxel
+//! coordinate of the first column of the last row of the first layer, i.e.
+//! the minimum corner.  `.` and spaces are unset voxels; a legend entry with
+//! the id `empty` marks voxels that are explicitly empty (which matters when
+//! the model is stamped with `StampMode::Overwrite`).
+//!
+//----------------------------------------------------------------------------//
+
+use crate::internal::*;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchematicBlock {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<[u8; 3]>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub occupied: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub walk_cost: Option<f32>,
+}
+
+impl SchematicBlock {
+    fn from_block(block: &Block) -> Self {
+        let color = match block.shader {
+            BlockShader::RGB(ref rgb) => Some([rgb.r, rgb.g, rgb.b]),
+            BlockShader::Empty => None,
+        };
+        Self {
+            id: block.id.clone(),
+            color,
+            occupied: block.occupied,
+            walk_cost: (block.walk_cost != 1.0).then_some(block.walk_cost),
+        }
+    }
+
+    fn to_block(&self) -> Block {
+        if self.id == "empty" {
+            return Block::empty();
+        }
+        let mut block = match self.color {
+            Some([r, g, b]) => Block::color(self.id.as_str(), r, g, b),
+            None => Block::new(self.id.as_str()),
+        };
+        block.occupied = self.occupied;
+        if let Some(walk_cost) = self.walk_cost {
+            block.walk_cost = walk_cost;
+        }
+        block
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schematic {
+    pub blocks: BTreeMap<char, SchematicBlock>,
+    #[serde(default)]
+    pub origin: [i32; 3],
+    pub layers: Vec<String>,
+}
+
+const UNSET_CHARS: [char; 2] = ['.', ' '];
+
+impl Schematic {
+    /// Fails if the model uses more distinct blocks than there are legend
+    /// characters.
+    pub fn from_voxel_set(voxel_set: &VoxelSet) -> Result<Self, Error> {
+        let mut bounds = IBox3::new();
+        let mut voxels: HashMap<IVec3, PaletteIndex> = HashMap::new();
+        for (p, index) in voxel_set.index_iter() {
+            bounds.add(p);
+            voxels.insert(p, index);
+        }
+        for p in voxel_set.explicit_empty_iter() {
+            bounds.add(p);
+            voxels.insert(p, PaletteIndex::zero());
+        }
+
+        // Assign each used block a legend character, preferring the first
+        // letter of its id so the grids stay readable.
+        let mut used: Vec<PaletteIndex> = voxels.values().copied().collect();
+        used.sort_by_key(|index| voxel_set.palette.get(*index).unwrap().id.clone());
+        used.dedup();
+
+        let mut blocks = BTreeMap::new();
+        let mut chars: HashMap<PaletteIndex, char> = HashMap::new();
+        let fallback = ('a'..='z').chain('A'..='Z').chain('0'..='9');
+        for index in used {
+            let block = voxel_set.palette.get(index).unwrap();
+            let preferred = if index.is_zero() {
+                Some('_')
+            } else {
+                block
+                    .id
+                    .chars()
+                    .next()
+                    .filter(|c| c.is_ascii_alphanumeric())
+            };
+            let c = preferred
+                .into_iter()
+                .chain(fallback.clone())
+                .find(|c| !blocks.contains_key(c))
+                .ok_or_else(|| schematic_error("too many distinct blocks for a schematic"))?;
+            blocks.insert(c, SchematicBlock::from_block(block));
+            chars.insert(index, c);
+        }
+
+        let mut layers = Vec::new();
+        if !voxels.is_empty() {
+            for z in bounds.min.z..=bounds.max.z {
+                let mut layer = String::new();
+                for y in (bounds.min.y..=bounds.max.y).rev() {
+                    for x in bounds.min.x..=bounds.max.x {
+                        let c = voxels
+                            .get(&IVec3::new(x, y, z))
+                            .map_or('.', |index| chars[index]);
+                        layer.push(c);
+                    }
+                    layer.push('\n');
+                }
+                layers.push(layer);
+            }
+        }
+
+        Ok(Self {
+            blocks,
+            origin: if voxels.is_empty() {
+                [0, 0, 0]
+            } else {
+                bounds.min.into()
+            },
+            layers,
+        })
+    }
+
+    pub fn to_voxel_set(&self) -> Result<VoxelSet, Error> {
+        let mut model = VoxelSet::new();
+        let mut indices: HashMap<char, PaletteIndex> = HashMap::new();
+        for (c, entry) in self.blocks.iter() {
+            if UNSET_CHARS.contains(c) {
+                return Err(schematic_error(&format!(
+                    "'{}' cannot be used in the legend",
+                    c
+                )));
+            }
+            let block = entry.to_block();
+            let index = if block.id == "empty" {
+                PaletteIndex::zero()
+            } else {
+                model.register_block(block)
+            };
+            indices.insert(*c, index);
+        }
+
+        let origin = IVec3::from(self.origin);
+        for (dz, layer) in self.layers.iter().enumerate() {
+            let rows: Vec<&str> = layer.lines().collect();
+            for (row, line) in rows.iter().enumerate() {
+                let dy = (rows.len() - row - 1) as i32;
+                for (dx, c) in line.chars().enumerate() {
+                    if UNSET_CHARS.contains(&c) {
+                        continue;
+                    }
+                    let Some(index) = indices.get(&c) else {
+                        return Err(schematic_error(&format!(
+                            "unknown block '{}' in layer {} row {}",
+                            c,
+                            dz,
+                            row + 1
+                        )));
+                    };
+                    model.set(origin + IVec3::new(dx as i32, dy, dz as i32), *index);
+                }
+            }
+        }
+        Ok(model)
+    }
+}
+
+fn schematic_error(message: &str) -> Error {
+    Error::Schematic(message.to_string())
+}
+
+impl VoxelSet {
+    pub fn from_schematic_str(s: &str) -> Result<VoxelSet, Error> {
+        let schematic: Schematic =
+            serde_yaml::from_str(s).map_err(|e| schematic_error(&e.to_string()))?;
+        schematic.to_voxel_set()
+    }
+
+    pub fn to_schematic_string(&self) -> Result<String, Error> {
+        serde_yaml::to_string(&Schematic::from_voxel_set(self)?)
+            .map_err(|e| schematic_error(&e.to_string()))
+    }
+
+    pub fn read_schematic_file(path: &str) -> Result<VoxelSet, Error> {
+        let s = std::fs::read_to_string(path)?;
+        VoxelSet::from_schematic_str(&s)
+    }
+
+    pub fn write_schematic_file(&self, path: &str) -> Result<(), Error> {
+        std::fs::write(path, self.to_schematic_string()?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SIGNPOST: &str = r#"
+blocks:
+  w: { id: wood, color: [90, 60, 30] }
+  s: { id: sign, color: [200, 180, 120], occupied: true }
+  _: { id: empty }
+origin: [-1, 0, 0]
+layers:
+  - |
+    .w.
+  - |
+    .w_
+  - |
+    sss
+"#;
+
+    #[test]
+    fn test_schematic_load() {
+        let model = VoxelSet::from_schematic_str(SIGNPOST).unwrap();
+        assert_eq!(model.get_voxel((0, 0, 0)).id, "wood");
+        assert_eq!(model.get_voxel((-1, 0, 2)).id, "sign");
+        assert!(model.get_voxel((1, 0, 2)).occupied);
+        assert_eq!(model.voxel_iter(false).len(), 5);
+        assert_eq!(model.explicit_empty_iter().count(), 1);
+
+        let err = VoxelSet::from_schematic_str("blocks: {}\nlayers: [\"x\"]\n");
+        assert!(matches!(err, Err(Error::Schematic(_))));
+    }
+
+    #[test]
+    fn test_schematic_round_trip() {
+        let model = VoxelSet::from_schematic_str(SIGNPOST).unwrap();
+        let text = model.to_schematic_string().unwrap();
+        let copy = VoxelSet::from_schematic_str(&text).unwrap();
+
+        assert_eq!(copy.voxel_iter(false).len(), 5);
+        assert_eq!(copy.explicit_empty_iter().count(), 1);
+        for (p, block) in model.voxel_iter(false) {
+            assert_eq!(copy.get_voxel(p), block);
+        }
+    }
+
+    #[test]
+    fn test_schematic_too_many_blocks() {
+        let mut model = VoxelSet::new();
+        for i in 0..80 {
+            let id = format!("block{}", i);
+            model.register_block(Block::color(id.as_str(), i as u8, 0, 0));
+            model.set_voxel((i, 0, 0), id.as_str());
+        }
+        assert!(matches!(
+            model.to_schematic_string(),
+            Err(Error::Schematic(_))
+        ));
+    }
+}
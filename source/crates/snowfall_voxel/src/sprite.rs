@@ -0,0 +1,285 @@
+//----------------------------------------------------------------------------//
+//! Conversion of 2D sprite images into VoxelSets.
+//!
+//! Each opaque pixel becomes a column of voxels extruded perpendicular to the
+//! image plane.  Pixels of the same color share a block named
+//! `color_<r>_<g>_<b>`.
+//!
+//----------------------------------------------------------------------------//
+
+use crate::internal::*;
+use bevy_color::{LinearRgba, Srgba};
+use image::RgbaImage;
+use std::path::{Path, PathBuf};
+
+/// The plane the image is placed in.  Image "up" is +Z for the upright planes
+/// and +Y for the flat plane; extrusion is along the remaining axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpritePlane {
+    XZ,
+    YZ,
+    XY,
+}
+
+/// How pixel colors are converted to block colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpriteColorSpace {
+    /// Pixel values are used as-is.
+    Srgb,
+    /// Pixel values are treated as sRGB and converted to linear values.
+    Linear,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpriteTint {
+    /// Every opaque pixel uses this color, keeping only the silhouette.
+    Replace((u8, u8, u8)),
+    /// Pixel colors are multiplied by this color.
+    Multiply((u8, u8, u8)),
+}
+
+pub struct SpriteOptions {
+    /// Relative paths are resolved against this directory
+    pub asset_root: PathBuf,
+    pub plane: SpritePlane,
+    /// Number of voxels each pixel is extruded by
+    pub depth: i32,
+    /// Pixels with an alpha below this value are skipped
+    pub alpha_threshold: u8,
+    pub color_space: SpriteColorSpace,
+    pub tint: Option<SpriteTint>,
+    /// Limits the number of distinct colors, mapping less frequent colors to
+    /// the nearest kept color
+    pub max_colors: Option<usize>,
+}
+
+impl Default for SpriteOptions {
+    fn default() -> Self {
+        Self {
+            asset_root: PathBuf::from("./assets"),
+            plane: SpritePlane::XZ,
+            depth: 1,
+            alpha_threshold: 1,
+            color_space: SpriteColorSpace::Srgb,
+            tint: None,
+            max_colors: None,
+        }
+    }
+}
+
+impl SpriteOptions {
+    pub fn with_asset_root<P: Into<PathBuf>>(mut self, asset_root: P) -> Self {
+        self.asset_root = asset_root.into();
+        self
+    }
+
+    pub fn with_plane(mut self, plane: SpritePlane) -> Self {
+        self.plane = plane;
+        self
+    }
+
+    pub fn with_depth(mut self, depth: i32) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn with_alpha_threshold(mut self, alpha_threshold: u8) -> Self {
+        self.alpha_threshold = alpha_threshold;
+        self
+    }
+
+    pub fn with_color_space(mut self, color_space: SpriteColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    pub fn with_tint(mut self, tint: Option<SpriteTint>) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    pub fn with_max_colors(mut self, max_colors: usize) -> Self {
+        self.max_colors = Some(max_colors);
+        self
+    }
+
+    pub fn resolve_path<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        let path = path.as_ref();
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.asset_root.join(path)
+        }
+    }
+
+    fn voxel_position(&self, u: i32, v: i32, d: i32) -> IVec3 {
+        match self.plane {
+            SpritePlane::XZ => IVec3::new(u, d, v),
+            SpritePlane::YZ => IVec3::new(d, u, v),
+            SpritePlane::XY => IVec3::new(u, v, d),
+        }
+    }
+
+    fn tinted(&self, rgb: (u8, u8, u8)) -> (u8, u8, u8) {
+        let mul = |a: u8, b: u8| ((a as u32 * b as u32) / 255) as u8;
+        match self.tint {
+            None => rgb,
+            Some(SpriteTint::Replace(c)) => c,
+            Some(SpriteTint::Multiply(c)) => (mul(rgb.0, c.0), mul(rgb.1, c.1), mul(rgb.2, c.2)),
+        }
+    }
+
+    fn block_color(&self, rgb: (u8, u8, u8)) -> (u8, u8, u8) {
+        match self.color_space {
+            SpriteColorSpace::Srgb => rgb,
+            SpriteColorSpace::Linear => {
+                let srgba = Srgba::new(
+                    rgb.0 as f32 / 255.0,
+                    rgb.1 as f32 / 255.0,
+                    rgb.2 as f32 / 255.0,
+                    1.0,
+                );
+                let lrgba = LinearRgba::from(srgba);
+                (
+                    (lrgba.red * 255.0).floor() as u8,
+                    (lrgba.green * 255.0).floor() as u8,
+                    (lrgba.blue * 255.0).floor() as u8,
+                )
+            }
+        }
+    }
+}
+
+impl VoxelSet {
+    /// Loads a sprite image from disk.  Relative paths are resolved against
+    /// the asset root in the options.
+    pub fn read_sprite_file<P: AsRef<Path>>(
+        path: P,
+        options: &SpriteOptions,
+    ) -> Result<VoxelSet, Error> {
+        let path = options.resolve_path(path);
+        let img = image::open(&path)
+            .map_err(|e| Error::Image(format!("{}: {}", path.display(), e)))?
+            .into_rgba8();
+        Ok(VoxelSet::from_sprite(&img, options))
+    }
+
+    /// Converts an image into a VoxelSet.  The image is centered horizontally
+    /// and its bottom row is placed at zero.
+    pub fn from_sprite(img: &RgbaImage, options: &SpriteOptions) -> VoxelSet {
+        let (width, height) = img.dimensions();
+
+        let mut pixels = Vec::new();
+        let mut counts: HashMap<(u8, u8, u8), usize> = HashMap::new();
+        for (x, y, pixel) in img.enumerate_pixels() {
+            let [r, g, b, a] = pixel.0;
+            if a < options.alpha_threshold {
+                continue;
+            }
+            let rgb = options.tinted((r, g, b));
+            *counts.entry(rgb).or_default() += 1;
+
+            let u = x as i32 - (width / 2) as i32 - 1;
+            let v = (height - y - 1) as i32;
+            pixels.push((u, v, rgb));
+        }
+
+        let max_colors = options.max_colors.unwrap_or(usize::MAX).max(1);
+        let (_, color_map) = quantize_colors(counts, max_colors);
+
+        let mut model = VoxelSet::new();
+        let mut block_cache: HashMap<(u8, u8, u8), PaletteIndex> = HashMap::new();
+        for (u, v, rgb) in pixels {
+            let rgb = color_map[&rgb];
+            let block = *block_cache.entry(rgb).or_insert_with(|| {
+                let name = format!("color_{}_{}_{}", rgb.0, rgb.1, rgb.2);
+                let (r, g, b) = options.block_color(rgb);
+                model.register_block(Block::color(name.as_str(), r, g, b))
+            });
+            for d in 0..options.depth.max(1) {
+                model.set(options.voxel_position(u, v, d), block);
+            }
+        }
+        model
+    }
+}
+
+/// Colors kept by quantize_colors and the kept color each input color maps to
+pub(crate) type QuantizedColors = (Vec<(u8, u8, u8)>, HashMap<(u8, u8, u8), (u8, u8, u8)>);
+
+/// Keeps the `max_colors` most frequent colors and maps every color to
+/// either itself or the nearest kept color.  Ties are broken by color value
+/// so the result is deterministic.
+pub(crate) fn quantize_colors(
+    counts: HashMap<(u8, u8, u8), usize>,
+    max_colors: usize,
+) -> QuantizedColors {
+    let mut colors: Vec<_> = counts.into_iter().collect();
+    colors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let palette: Vec<(u8, u8, u8)> = colors.iter().take(max_colors).map(|(c, _)| *c).collect();
+    let mut mapping = HashMap::new();
+    for c in palette.iter() {
+        mapping.insert(*c, *c);
+    }
+    for (c, _) in colors.iter().skip(max_colors) {
+        let nearest = palette
+            .iter()
+            .min_by_key(|p| {
+                let dr = p.0 as i32 - c.0 as i32;
+                let dg = p.1 as i32 - c.1 as i32;
+                let db = p.2 as i32 - c.2 as i32;
+                dr * dr + dg * dg + db * db
+            })
+            .unwrap();
+        mapping.insert(*c, *nearest);
+    }
+    (palette, mapping)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::Rgba;
+
+    fn sample_image() -> RgbaImage {
+        let mut img = RgbaImage::new(4, 2);
+        img.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        img.put_pixel(1, 0, Rgba([250, 0, 0, 255]));
+        img.put_pixel(2, 1, Rgba([0, 0, 255, 255]));
+        img.put_pixel(3, 1, Rgba([0, 255, 0, 5]));
+        img
+    }
+
+    #[test]
+    fn test_sprite_options() {
+        let img = sample_image();
+
+        let model = VoxelSet::from_sprite(&img, &SpriteOptions::default());
+        assert_eq!(model.voxel_iter(false).len(), 4);
+        assert_eq!(model.get_voxel((-3, 0, 1)).id, "color_255_0_0");
+        assert_eq!(model.get_voxel((-1, 0, 0)).id, "color_0_0_255");
+
+        let options = SpriteOptions::default()
+            .with_plane(SpritePlane::XY)
+            .with_depth(2)
+            .with_alpha_threshold(10)
+            .with_max_colors(2);
+        let model = VoxelSet::from_sprite(&img, &options);
+        assert_eq!(model.voxel_iter(false).len(), 6);
+        assert_eq!(
+            model.get_voxel((-2, 1, 1)).id,
+            model.get_voxel((-3, 1, 0)).id
+        );
+
+        let options = SpriteOptions::default().with_tint(Some(SpriteTint::Replace((1, 2, 3))));
+        let model = VoxelSet::from_sprite(&img, &options);
+        assert_eq!(model.get_voxel((-1, 0, 0)).id, "color_1_2_3");
+    }
+
+    #[test]
+    fn test_missing_sprite_is_an_error() {
+        let options = SpriteOptions::default().with_asset_root("/nonexistent");
+        assert!(VoxelSet::read_sprite_file("missing.png", &options).is_err());
+    }
+}
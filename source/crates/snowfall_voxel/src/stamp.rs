@@ -0,0 +1,248 @@
+use crate::internal::*;
+
+/// Determines how a stamped model interacts with the voxels already present
+/// in the target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StampMode {
+    /// The stamp replaces the target, including clearing target voxels where
+    /// the stamp has explicitly empty voxels (e.g. the air inside a house).
+    Overwrite,
+
+    /// Keeps everything already in the target: only empty target voxels are
+    /// written.
+    FillEmpty,
+
+    /// Non-empty stamp voxels replace the target, but existing target voxels
+    /// are never cleared.
+    OverwriteSolid,
+}
+
+/// Options for writing ("baking") a VoxelSet into another VoxelSet or a
+/// VoxelGrid.
+///
+/// `block_map` remaps block ids in the stamp to block ids that already exist
+/// in the target, e.g. stamping a house's "wall" blocks as the terrain's
+/// "stone".  Blocks that are not remapped are merged into the target palette.
+/// Mapping to a block the target does not have is an error.
+pub struct StampOptions {
+    pub offset: IVec3,
+    pub orientation: Orientation,
+    pub mode: StampMode,
+    pub block_map: HashMap<String, String>,
+}
+
+impl StampOptions {
+    pub fn new(offset: IVec3) -> Self {
+        Self {
+            offset,
+            orientation: Orientation::default(),
+            mode: StampMode::FillEmpty,
+            block_map: HashMap::new(),
+        }
+    }
+
+    pub fn with_orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    pub fn with_mode(mut self, mode: StampMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn map_block<S, T>(mut self, from: S, to: T) -> Self
+    where
+        S: Into<String>,
+        T: Into<String>,
+    {
+        self.block_map.insert(from.into(), to.into());
+        self
+    }
+
+    fn target_position(&self, p: IVec3) -> IVec3 {
+        self.orientation.apply(p) + self.offset
+    }
+}
+
+impl VoxelSet {
+    /// Writes `source` into this set.
+    pub fn stamp(&mut self, source: &VoxelSet, options: &StampOptions) -> Result<(), Error> {
+        for to in options.block_map.values() {
+            if self.palette.find_id(to).is_none() {
+                return Err(Error::UnknownBlock(to.clone()));
+            }
+        }
+        let mut remap: HashMap<PaletteIndex, PaletteIndex> = HashMap::new();
+        for (index, block) in source.palette.iter() {
+            let target = match options.block_map.get(&block.id) {
+                Some(to) => self.palette.index_for_id(to),
+                None => self.palette.merge_block(block),
+            };
+            remap.insert(index, target);
+        }
+
+        if options.mode == StampMode::Overwrite {
+            for p in source.explicit_empty_iter() {
+                self.clear_voxel(options.target_position(p));
+            }
+        }
+        for (p, index) in source.index_iter() {
+            let q = options.target_position(p);
+            let index = remap[&index];
+            match options.mode {
+                StampMode::Overwrite => self.set(q, index),
+                StampMode::FillEmpty => {
+                    if !index.is_zero() && self.is_empty(q) {
+                        self.set(q, index);
+                    }
+                }
+                StampMode::OverwriteSolid => {
+                    if !index.is_zero() {
+                        self.set(q, index);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl VoxelGrid {
+    /// Writes `source` into the grid.  Chunks touched by the stamp are paged
+    /// in or generated as needed before being written.
+    pub fn stamp(&mut self, source: &VoxelSet, options: &StampOptions) -> Result<(), Error> {
+        let mut remap: HashMap<PaletteIndex, usize> = HashMap::new();
+        for (index, block) in source.palette.iter() {
+            let target = match options.block_map.get(&block.id) {
+                Some(to) => self
+                    .block_index(to)
+                    .ok_or_else(|| Error::UnknownBlock(to.clone()))?,
+                None => self.ensure_block(block),
+            };
+            remap.insert(index, target);
+        }
+
+        if options.mode == StampMode::Overwrite {
+            for p in source.explicit_empty_iter() {
+                self.set_index(options.target_position(p), 0);
+            }
+        }
+        for (p, index) in source.index_iter() {
+            let q = options.target_position(p);
+            let index = remap[&index];
+            let write = match options.mode {
+                StampMode::Overwrite => true,
+                StampMode::FillEmpty => index != 0 && self.is_empty(q),
+                StampMode::OverwriteSolid => index != 0,
+            };
+            if write {
+                self.set_index(q, index);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Scene2 {
+    /// Permanently merges an object (and, for groups, all of its children)
+    /// into the scene terrain.  Objects that are not voxel based are ignored.
+    pub fn bake_into_terrain(&mut self, object: &Object, mode: StampMode) -> Result<(), Error> {
+        match &object.imp {
+            ObjectImp::VoxelSet(voxel_set) => {
+                let options = StampOptions::new(object.position)
+                    .with_orientation(object.orientation)
+                    .with_mode(mode);
+                self.terrain.stamp(voxel_set, &options)?;
+            }
+            ObjectImp::Group(group) => {
+                for child in group.objects.iter() {
+                    self.bake_into_terrain(child, mode)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ground() -> VoxelSet {
+        let mut model = VoxelSet::new();
+        model.register_block(Block::color("dirt", 20, 10, 5));
+        model.fill_box((-2, -2, 0), (2, 2, 1), "dirt");
+        model
+    }
+
+    fn boulder() -> VoxelSet {
+        let mut model = VoxelSet::new();
+        model.register_block(Block::color("stone", 50, 50, 50));
+        model.fill_box((0, 0, 0), (1, 0, 1), "stone");
+        model.set_voxel((0, 1, 0), "empty");
+        model
+    }
+
+    #[test]
+    fn test_stamp_modes() {
+        let options = StampOptions::new(IVec3::new(0, 0, 1));
+
+        assert_eq!(options.mode, StampMode::FillEmpty);
+
+        let mut target = ground();
+        target
+            .stamp(&boulder(), &options.with_mode(StampMode::FillEmpty))
+            .unwrap();
+        assert_eq!(target.get_voxel((0, 0, 1)).id, "dirt");
+        assert_eq!(target.get_voxel((0, 0, 2)).id, "stone");
+
+        let options = StampOptions::new(IVec3::new(0, 0, 1));
+        let mut target = ground();
+        target
+            .stamp(&boulder(), &options.with_mode(StampMode::OverwriteSolid))
+            .unwrap();
+        assert_eq!(target.get_voxel((0, 0, 1)).id, "stone");
+        assert_eq!(target.get_voxel((0, 1, 1)).id, "dirt");
+
+        let options = StampOptions::new(IVec3::new(0, 0, 1));
+        let mut target = ground();
+        target
+            .stamp(&boulder(), &options.with_mode(StampMode::Overwrite))
+            .unwrap();
+        assert_eq!(target.get_voxel((0, 0, 1)).id, "stone");
+        assert_eq!(target.get_voxel((0, 1, 1)).id, "empty");
+    }
+
+    #[test]
+    fn test_stamp_orientation_and_block_map() {
+        let options = StampOptions::new(IVec3::new(0, 0, 2))
+            .with_orientation(Orientation::rotation_z(1))
+            .map_block("stone", "dirt");
+
+        let mut target = ground();
+        target.stamp(&boulder(), &options).unwrap();
+        assert_eq!(target.get_voxel((0, 1, 2)).id, "dirt");
+        assert_eq!(target.get_voxel((1, 0, 2)).id, "empty");
+        assert_eq!(target.palette.find_id("stone"), None);
+    }
+
+    #[test]
+    fn test_stamp_unknown_block_map_target() {
+        let options = StampOptions::new(IVec3::new(0, 0, 1))
+            .with_mode(StampMode::Overwrite)
+            .map_block("stone", "granite");
+
+        let mut target = ground();
+        let before = target.clone();
+        assert!(matches!(
+            target.stamp(&boulder(), &options),
+            Err(Error::UnknownBlock(id)) if id == "granite"
+        ));
+        assert_eq!(
+            target.get_voxel((0, 0, 1)).id,
+            before.get_voxel((0, 0, 1)).id
+        );
+    }
+}
@@ -0,0 +1,241 @@
+use crate::internal::*;
+
+/// Axis-aligned orientation of a model, constrained to mirroring and 90 degree
+/// rotations so that voxels always map exactly onto other voxels.
+///
+/// The transform is applied as: mirror, then rotate about X, then Y, then Z.
+/// All transforms pivot around the voxel at the origin, which matches how the
+/// generators center their models.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Orientation {
+    #[serde(default)]
+    pub mirror_x: bool,
+    #[serde(default)]
+    pub mirror_y: bool,
+    #[serde(default)]
+    pub mirror_z: bool,
+
+    /// Number of counter-clockwise quarter turns about each axis
+    #[serde(default)]
+    pub rotate_x: u8,
+    #[serde(default)]
+    pub rotate_y: u8,
+    #[serde(default)]
+    pub rotate_z: u8,
+}
+
+impl Orientation {
+    pub fn identity() -> Self {
+        Self::default()
+    }
+
+    pub fn rotation_z(quarter_turns: i32) -> Self {
+        Self {
+            rotate_z: quarter_turns.rem_euclid(4) as u8,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        !self.mirror_x
+            && !self.mirror_y
+            && !self.mirror_z
+            && self.rotate_x.is_multiple_of(4)
+            && self.rotate_y.is_multiple_of(4)
+            && self.rotate_z.is_multiple_of(4)
+    }
+
+    /// Maps a voxel coordinate through the orientation.
+    pub fn apply(&self, p: IVec3) -> IVec3 {
+        let mut p = p;
+        if self.mirror_x {
+            p.x = -p.x;
+        }
+        if self.mirror_y {
+            p.y = -p.y;
+        }
+        if self.mirror_z {
+            p.z = -p.z;
+        }
+        for _ in 0..self.rotate_x % 4 {
+            p = IVec3::new(p.x, -p.z, p.y);
+        }
+        for _ in 0..self.rotate_y % 4 {
+            p = IVec3::new(p.z, p.y, -p.x);
+        }
+        for _ in 0..self.rotate_z % 4 {
+            p = IVec3::new(-p.y, p.x, p.z);
+        }
+        p
+    }
+}
+
+/// Transforms
+///
+/// All transforms return a new VoxelSet that shares the palette and attributes
+/// of the original.
+impl VoxelSet {
+    /// Returns a copy with every voxel moved to the position returned by `f`.
+    pub fn map_positions<F>(&self, f: F) -> VoxelSet
+    where
+        F: Fn(IVec3) -> IVec3,
+    {
+        let mut result = self.empty_copy();
+        for (p, index) in self.index_iter() {
+            result.set(f(p), index);
+        }
+        for anchor in result.metadata.anchors.values_mut() {
+            *anchor = f(*anchor);
+        }
+        result
+    }
+
+    pub fn oriented(&self, orientation: &Orientation) -> VoxelSet {
+        self.map_positions(|p| orientation.apply(p))
+    }
+
+    /// Rotates the set counter-clockwise (looking down the axis) by the given
+    /// number of quarter turns.  Negative values rotate clockwise.
+    pub fn rotate_x(&self, quarter_turns: i32) -> VoxelSet {
+        self.oriented(&Orientation {
+            rotate_x: quarter_turns.rem_euclid(4) as u8,
+            ..Orientation::default()
+        })
+    }
+
+    pub fn rotate_y(&self, quarter_turns: i32) -> VoxelSet {
+        self.oriented(&Orientation {
+            rotate_y: quarter_turns.rem_euclid(4) as u8,
+            ..Orientation::default()
+        })
+    }
+
+    pub fn rotate_z(&self, quarter_turns: i32) -> VoxelSet {
+        self.oriented(&Orientation::rotation_z(quarter_turns))
+    }
+
+    pub fn mirror_x(&self) -> VoxelSet {
+        self.map_positions(|p| IVec3::new(-p.x, p.y, p.z))
+    }
+
+    pub fn mirror_y(&self) -> VoxelSet {
+        self.map_positions(|p| IVec3::new(p.x, -p.y, p.z))
+    }
+
+    pub fn mirror_z(&self) -> VoxelSet {
+        self.map_positions(|p| IVec3::new(p.x, p.y, -p.z))
+    }
+
+    /// Replaces each voxel with a factor x factor x factor cube of the same
+    /// block.
+    pub fn upscale(&self, factor: i32) -> VoxelSet {
+        assert!(factor >= 1, "upscale factor must be positive");
+
+        let mut result = self.empty_copy();
+        for (p, index) in self.index_iter() {
+            let base = p * factor;
+            for dz in 0..factor {
+                for dy in 0..factor {
+                    for dx in 0..factor {
+                        result.set(base + IVec3::new(dx, dy, dz), index);
+                    }
+                }
+            }
+        }
+        for anchor in result.metadata.anchors.values_mut() {
+            *anchor *= factor;
+        }
+        result
+    }
+
+    /// Shrinks the set by the given factor using nearest-neighbour sampling:
+    /// each output voxel takes the block at the minimum corner of the
+    /// corresponding factor x factor x factor region.
+    pub fn downscale(&self, factor: i32) -> VoxelSet {
+        assert!(factor >= 1, "downscale factor must be positive");
+
+        let mut result = self.empty_copy();
+        for (p, index) in self.index_iter() {
+            let r = p.rem_euclid(IVec3::splat(factor));
+            if r != IVec3::ZERO {
+                continue;
+            }
+            result.set(p.div_euclid(IVec3::splat(factor)), index);
+        }
+        for anchor in result.metadata.anchors.values_mut() {
+            *anchor = anchor.div_euclid(IVec3::splat(factor));
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample_set() -> VoxelSet {
+        let mut model = VoxelSet::new();
+        model.register_block(Block::color("a", 255, 0, 0));
+        model.register_block(Block::color("b", 0, 255, 0));
+        model.set((0, 0, 0), "a");
+        model.set((2, 0, 0), "a");
+        model.set((2, 1, 3), "b");
+        model
+    }
+
+    #[test]
+    fn test_rotate_z_quarter_turn() {
+        let model = sample_set().rotate_z(1);
+        assert_eq!(model.get_voxel((0, 2, 0)).id, "a");
+        assert_eq!(model.get_voxel((-1, 2, 3)).id, "b");
+        assert_eq!(model.get_voxel((2, 0, 0)).id, "empty");
+    }
+
+    #[test]
+    fn test_full_rotations_are_identity() {
+        let model = sample_set();
+        for rotated in [
+            model.rotate_x(4),
+            model.rotate_y(2).rotate_y(2),
+            model.rotate_z(-1).rotate_z(1),
+            model.mirror_x().mirror_x(),
+        ] {
+            for (p, block) in model.voxel_iter(false) {
+                assert_eq!(rotated.get_voxel(p).id, block.id);
+            }
+            assert_eq!(rotated.voxel_iter(false).len(), 3);
+        }
+    }
+
+    #[test]
+    fn test_upscale_then_downscale() {
+        let model = sample_set();
+        let up = model.upscale(3);
+        assert_eq!(up.voxel_iter(false).len(), 3 * 27);
+        assert_eq!(up.get_voxel((8, 5, 11)).id, "b");
+
+        let down = up.downscale(3);
+        assert_eq!(down.voxel_iter(false).len(), 3);
+        assert_eq!(down.get_voxel((2, 1, 3)).id, "b");
+    }
+
+    #[test]
+    fn test_anchors_follow_transforms() {
+        let mut model = sample_set();
+        model.set_anchor("entrance", IVec3::new(2, 1, 3));
+
+        assert_eq!(
+            model.rotate_z(1).anchor("entrance"),
+            Some(IVec3::new(-1, 2, 3))
+        );
+        assert_eq!(
+            model.upscale(3).anchor("entrance"),
+            Some(IVec3::new(6, 3, 9))
+        );
+        assert_eq!(
+            model.upscale(3).downscale(3).anchor("entrance"),
+            Some(IVec3::new(2, 1, 3))
+        );
+    }
+}
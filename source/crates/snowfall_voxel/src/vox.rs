@@ -0,0 +1,671 @@
+//----------------------------------------------------------------------------//
+//! Import and export of the MagicaVoxel `.vox` format.
+//!
+//! See https://github.com/ephtracy/voxel-model/blob/master/MagicaVoxel-file-format-vox.txt
+//! and the extension for the scene graph chunks (nTRN, nGRP, nSHP).
+//!
+//----------------------------------------------------------------------------//
+
+use crate::internal::*;
+use crate::sprite::quantize_colors;
+
+const VOX_MAGIC: &[u8; 4] = b"VOX ";
+const VOX_VERSION: i32 = 150;
+
+/// A single .vox model can be at most 256 voxels along each axis.
+const VOX_MAX_DIM: i32 = 256;
+
+impl VoxelSet {
+    // ------------------------------------------------------------------------
+    // Export
+    // ------------------------------------------------------------------------
+
+    pub fn write_vox_file(&self, path: &str) -> Result<(), Error> {
+        std::fs::write(path, self.to_vox_bytes())?;
+        Ok(())
+    }
+
+    /// Encodes the set as a .vox file.
+    ///
+    /// Sets larger than 256 voxels along any axis are split into multiple
+    /// models, positioned with a scene graph so they line up when opened in
+    /// MagicaVoxel.  If the set uses more than 255 distinct colors, the least
+    /// used colors are mapped to the nearest of the 255 most common ones.
+    pub fn to_vox_bytes(&self) -> Vec<u8> {
+        let (palette, color_indices) = build_vox_palette(self);
+
+        // Bucket the voxels into 256^3 regions
+        let bounds = self.bounds();
+        let mut regions: HashMap<IVec3, Vec<(IVec3, u8)>> = HashMap::new();
+        for (p, block) in self.voxel_iter(false) {
+            let BlockShader::RGB(ref rgb) = block.shader else {
+                continue;
+            };
+            let color_index = color_indices[&(rgb.r, rgb.g, rgb.b)];
+            let region = (p - bounds.min).div_euclid(IVec3::splat(VOX_MAX_DIM));
+            regions.entry(region).or_default().push((p, color_index));
+        }
+        // Sort so the output is deterministic for a given set
+        let mut regions: Vec<_> = regions.into_iter().collect();
+        regions.sort_by_key(|(r, _)| (r.z, r.y, r.x));
+
+        let mut children = Vec::new();
+        let mut translations = Vec::new();
+        for (_, voxels) in regions.iter_mut() {
+            voxels.sort_by_key(|(p, _)| (p.z, p.y, p.x));
+            let mut region_bounds = IBox3::new();
+            for (p, _) in voxels.iter() {
+                region_bounds.add(*p);
+            }
+            let min = region_bounds.min;
+            let size = IVec3::new(
+                region_bounds.length_x(),
+                region_bounds.length_y(),
+                region_bounds.length_z(),
+            );
+
+            let mut content = Vec::new();
+            write_i32(&mut content, size.x);
+            write_i32(&mut content, size.y);
+            write_i32(&mut content, size.z);
+            write_chunk(&mut children, b"SIZE", &content, &[]);
+
+            let mut content = Vec::new();
+            write_i32(&mut content, voxels.len() as i32);
+            for (p, color_index) in voxels.iter() {
+                let local = *p - min;
+                content.extend_from_slice(&[local.x as u8, local.y as u8, local.z as u8]);
+                content.push(*color_index);
+            }
+            write_chunk(&mut children, b"XYZI", &content, &[]);
+
+            translations.push(min + size / 2);
+        }
+
+        write_scene_graph(&mut children, &translations);
+
+        let mut content = Vec::with_capacity(256 * 4);
+        for i in 0..256 {
+            let (r, g, b) = palette.get(i).copied().unwrap_or((0, 0, 0));
+            content.extend_from_slice(&[r, g, b, 255]);
+        }
+        write_chunk(&mut children, b"RGBA", &content, &[]);
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(VOX_MAGIC);
+        write_i32(&mut bytes, VOX_VERSION);
+        write_chunk(&mut bytes, b"MAIN", &[], &children);
+        bytes
+    }
+
+    // ------------------------------------------------------------------------
+    // Import
+    // ------------------------------------------------------------------------
+
+    pub fn read_vox_file(path: &str) -> Result<Self, Error> {
+        let bytes = std::fs::read(path)?;
+        Self::from_vox_bytes(&bytes)
+    }
+
+    /// Decodes a .vox file.  Each color index used by the file becomes a
+    /// Block::color entry named `vox_<index>`.  All models in the file are
+    /// merged into the single returned set using the transforms from the
+    /// scene graph, if one is present.
+    pub fn from_vox_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = VoxReader { bytes, pos: 0 };
+        if reader.take(4)? != VOX_MAGIC {
+            return Err(Error::FileHeader(
+                String::from_utf8_lossy(&bytes[..4.min(bytes.len())]).to_string(),
+            ));
+        }
+        let _version = reader.i32()?;
+
+        let (id, main) = reader.chunk()?;
+        if id != *b"MAIN" {
+            return Err(vox_error("expected MAIN chunk"));
+        }
+
+        let mut models: Vec<(IVec3, Vec<[u8; 4]>)> = Vec::new();
+        let mut size = None;
+        let mut palette: Option<Vec<(u8, u8, u8)>> = None;
+        let mut nodes: HashMap<i32, VoxNode> = HashMap::new();
+
+        let mut reader = VoxReader {
+            bytes: main.children,
+            pos: 0,
+        };
+        while !reader.is_done() {
+            let (id, chunk) = reader.chunk()?;
+            let mut content = VoxReader {
+                bytes: chunk.content,
+                pos: 0,
+            };
+            match &id {
+                b"SIZE" => {
+                    size = Some(IVec3::new(content.i32()?, content.i32()?, content.i32()?));
+                }
+                b"XYZI" => {
+                    let size = size.take().ok_or_else(|| vox_error("XYZI without SIZE"))?;
+                    let count = content.len()?;
+                    if count > content.remaining() / 4 {
+                        return Err(vox_error("XYZI count exceeds chunk size"));
+                    }
+                    let mut voxels = Vec::with_capacity(count);
+                    for _ in 0..count {
+                        let v = content.take(4)?;
+                        voxels.push([v[0], v[1], v[2], v[3]]);
+                    }
+                    models.push((size, voxels));
+                }
+                b"RGBA" => {
+                    let mut colors = Vec::with_capacity(256);
+                    for _ in 0..256 {
+                        let c = content.take(4)?;
+                        colors.push((c[0], c[1], c[2]));
+                    }
+                    palette = Some(colors);
+                }
+                b"nTRN" => {
+                    let node_id = content.i32()?;
+                    let _attributes = content.dict()?;
+                    let child = content.i32()?;
+                    let _reserved = content.i32()?;
+                    let _layer = content.i32()?;
+                    let frame_count = content.i32()?;
+                    let mut translation = IVec3::ZERO;
+                    let mut rotation = None;
+                    for frame in 0..frame_count {
+                        let dict = content.dict()?;
+                        if frame != 0 {
+                            continue;
+                        }
+                        if let Some(t) = dict.get("_t") {
+                            translation = parse_vox_translation(t)?;
+                        }
+                        if let Some(r) = dict.get("_r") {
+                            rotation = Some(parse_vox_rotation(r)?);
+                        }
+                    }
+                    nodes.insert(
+                        node_id,
+                        VoxNode::Transform {
+                            child,
+                            translation,
+                            rotation,
+                        },
+                    );
+                }
+                b"nGRP" => {
+                    let node_id = content.i32()?;
+                    let _attributes = content.dict()?;
+                    let count = content.i32()?.max(0);
+                    let mut children = Vec::new();
+                    for _ in 0..count {
+                        children.push(content.i32()?);
+                    }
+                    nodes.insert(node_id, VoxNode::Group { children });
+                }
+                b"nSHP" => {
+                    let node_id = content.i32()?;
+                    let _attributes = content.dict()?;
+                    let count = content.i32()?.max(0);
+                    let mut model_ids = Vec::new();
+                    for _ in 0..count {
+                        model_ids.push(content.i32()?);
+                        let _model_attributes = content.dict()?;
+                    }
+                    nodes.insert(node_id, VoxNode::Shape { models: model_ids });
+                }
+                _ => {}
+            }
+        }
+
+        // Determine where each model is placed.  Files without a scene graph
+        // place every model at its local coordinates.
+        let mut placements: Vec<(usize, VoxTransform)> = Vec::new();
+        if nodes.contains_key(&0) {
+            collect_vox_placements(&nodes, 0, VoxTransform::identity(), &mut placements, 0)?;
+        } else {
+            for i in 0..models.len() {
+                placements.push((i, VoxTransform::identity()));
+            }
+        }
+
+        let palette = palette.unwrap_or_else(default_vox_palette);
+        let mut voxel_set = VoxelSet::new();
+        let mut blocks: HashMap<u8, PaletteIndex> = HashMap::new();
+        for (model_index, transform) in placements {
+            let Some((size, voxels)) = models.get(model_index) else {
+                return Err(vox_error("shape references a missing model"));
+            };
+            let half = *size / 2;
+            for [x, y, z, color_index] in voxels.iter().copied() {
+                if color_index == 0 {
+                    continue;
+                }
+                let index = *blocks.entry(color_index).or_insert_with(|| {
+                    let (r, g, b) = palette[color_index as usize - 1];
+                    let name = format!("vox_{}", color_index);
+                    voxel_set.register_block(Block::color(name, r, g, b))
+                });
+                let local = IVec3::new(x as i32, y as i32, z as i32);
+                let p = if transform.centered {
+                    transform.apply(local - half)
+                } else {
+                    transform.apply(local)
+                };
+                voxel_set.set(p, index);
+            }
+        }
+        Ok(voxel_set)
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Palette
+// ----------------------------------------------------------------------------
+
+/// Colors of a .vox palette and the color index of each RGB color mapped to it
+type VoxPalette = (Vec<(u8, u8, u8)>, HashMap<(u8, u8, u8), u8>);
+
+/// Returns the colors for the .vox palette (color index i + 1 is entry i) and
+/// a map from each RGB color used in the set to its color index.
+fn build_vox_palette(voxel_set: &VoxelSet) -> VoxPalette {
+    let mut counts: HashMap<(u8, u8, u8), usize> = HashMap::new();
+    for (_, block) in voxel_set.voxel_iter(false) {
+        if let BlockShader::RGB(ref rgb) = block.shader {
+            *counts.entry((rgb.r, rgb.g, rgb.b)).or_default() += 1;
+        }
+    }
+
+    let (palette, mapping) = quantize_colors(counts, 255);
+    let mut indices = HashMap::new();
+    for (c, nearest) in mapping {
+        let i = palette.iter().position(|p| *p == nearest).unwrap();
+        indices.insert(c, (i + 1) as u8);
+    }
+    (palette, indices)
+}
+
+/// The palette MagicaVoxel uses for files that do not contain an RGBA chunk:
+/// a 6x6x6 color cube followed by red, green, blue, and gray ramps.
+fn default_vox_palette() -> Vec<(u8, u8, u8)> {
+    let levels = [0xff, 0xcc, 0x99, 0x66, 0x33, 0x00];
+    let ramp = [0xee, 0xdd, 0xbb, 0xaa, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+
+    let mut palette = Vec::with_capacity(256);
+    for r in levels {
+        for g in levels {
+            for b in levels {
+                palette.push((r, g, b));
+            }
+        }
+    }
+    palette.pop(); // black is not part of the cube
+    for v in ramp {
+        palette.push((v, 0, 0));
+    }
+    for v in ramp {
+        palette.push((0, v, 0));
+    }
+    for v in ramp {
+        palette.push((0, 0, v));
+    }
+    for v in ramp {
+        palette.push((v, v, v));
+    }
+    palette.push((0, 0, 0));
+    palette
+}
+
+// ----------------------------------------------------------------------------
+// Scene graph
+// ----------------------------------------------------------------------------
+
+enum VoxNode {
+    Transform {
+        child: i32,
+        translation: IVec3,
+        rotation: Option<u8>,
+    },
+    Group {
+        children: Vec<i32>,
+    },
+    Shape {
+        models: Vec<i32>,
+    },
+}
+
+/// Integer affine transform: a signed permutation matrix plus a translation.
+#[derive(Clone, Copy)]
+struct VoxTransform {
+    rows: [(usize, i32); 3],
+    translation: IVec3,
+    centered: bool,
+}
+
+impl VoxTransform {
+    fn identity() -> Self {
+        Self {
+            rows: [(0, 1), (1, 1), (2, 1)],
+            translation: IVec3::ZERO,
+            centered: false,
+        }
+    }
+
+    /// Decodes the packed `_r` rotation byte of an nTRN frame, which must
+    /// have been checked by parse_vox_rotation.
+    fn from_rotation(r: u8, translation: IVec3) -> Self {
+        let i0 = (r & 0b11) as usize;
+        let i1 = ((r >> 2) & 0b11) as usize;
+        let i2 = 3 - i0 - i1;
+        let sign = |bit: u8| if r & (1 << bit) != 0 { -1 } else { 1 };
+        Self {
+            rows: [(i0, sign(4)), (i1, sign(5)), (i2, sign(6))],
+            translation,
+            centered: true,
+        }
+    }
+
+    fn apply(&self, p: IVec3) -> IVec3 {
+        let v = p.to_array();
+        IVec3::new(
+            v[self.rows[0].0] * self.rows[0].1,
+            v[self.rows[1].0] * self.rows[1].1,
+            v[self.rows[2].0] * self.rows[2].1,
+        ) + self.translation
+    }
+
+    /// Returns the transform equivalent to applying `child` and then `self`.
+    fn then(&self, child: &VoxTransform) -> VoxTransform {
+        let rows = self.rows.map(|(axis, sign)| {
+            let (child_axis, child_sign) = child.rows[axis];
+            (child_axis, sign * child_sign)
+        });
+        let translation = self.apply(child.translation);
+        VoxTransform {
+            rows,
+            translation,
+            centered: self.centered || child.centered,
+        }
+    }
+}
+
+fn collect_vox_placements(
+    nodes: &HashMap<i32, VoxNode>,
+    node_id: i32,
+    parent: VoxTransform,
+    placements: &mut Vec<(usize, VoxTransform)>,
+    depth: usize,
+) -> Result<(), Error> {
+    if depth > 64 {
+        return Err(vox_error("scene graph is too deep or cyclic"));
+    }
+    match nodes.get(&node_id) {
+        Some(VoxNode::Transform {
+            child,
+            translation,
+            rotation,
+        }) => {
+            let local = VoxTransform::from_rotation(rotation.unwrap_or(0b0000_0100), *translation);
+            let transform = parent.then(&local);
+            collect_vox_placements(nodes, *child, transform, placements, depth + 1)
+        }
+        Some(VoxNode::Group { children }) => {
+            for child in children {
+                collect_vox_placements(nodes, *child, parent, placements, depth + 1)?;
+            }
+            Ok(())
+        }
+        Some(VoxNode::Shape { models }) => {
+            for model in models {
+                placements.push((*model as usize, parent));
+            }
+            Ok(())
+        }
+        None => Err(vox_error("scene graph references a missing node")),
+    }
+}
+
+/// Writes a minimal scene graph: a root transform, a group, and a
+/// transform + shape pair for each model.
+fn write_scene_graph(out: &mut Vec<u8>, translations: &[IVec3]) {
+    let empty: &[(&str, String)] = &[];
+
+    let mut content = Vec::new();
+    write_i32(&mut content, 0);
+    write_dict(&mut content, empty);
+    write_i32(&mut content, 1);
+    write_i32(&mut content, -1);
+    write_i32(&mut content, -1);
+    write_i32(&mut content, 1);
+    write_dict(&mut content, empty);
+    write_chunk(out, b"nTRN", &content, &[]);
+
+    let mut content = Vec::new();
+    write_i32(&mut content, 1);
+    write_dict(&mut content, empty);
+    write_i32(&mut content, translations.len() as i32);
+    for i in 0..translations.len() {
+        write_i32(&mut content, 2 + 2 * i as i32);
+    }
+    write_chunk(out, b"nGRP", &content, &[]);
+
+    for (i, t) in translations.iter().enumerate() {
+        let transform_id = 2 + 2 * i as i32;
+
+        let mut content = Vec::new();
+        write_i32(&mut content, transform_id);
+        write_dict(&mut content, empty);
+        write_i32(&mut content, transform_id + 1);
+        write_i32(&mut content, -1);
+        write_i32(&mut content, 0);
+        write_i32(&mut content, 1);
+        write_dict(&mut content, &[("_t", format!("{} {} {}", t.x, t.y, t.z))]);
+        write_chunk(out, b"nTRN", &content, &[]);
+
+        let mut content = Vec::new();
+        write_i32(&mut content, transform_id + 1);
+        write_dict(&mut content, empty);
+        write_i32(&mut content, 1);
+        write_i32(&mut content, i as i32);
+        write_dict(&mut content, empty);
+        write_chunk(out, b"nSHP", &content, &[]);
+    }
+}
+
+fn parse_vox_translation(s: &str) -> Result<IVec3, Error> {
+    let parts: Vec<i32> = s
+        .split_whitespace()
+        .map(|v| v.parse::<i32>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| vox_error("bad _t"))?;
+    if parts.len() != 3 {
+        return Err(vox_error("bad _t"));
+    }
+    Ok(IVec3::new(parts[0], parts[1], parts[2]))
+}
+
+/// Parses the packed `_r` rotation byte of an nTRN frame.  The first two rows
+/// must select different axes, each in 0..3.
+fn parse_vox_rotation(s: &str) -> Result<u8, Error> {
+    let r = s.parse::<u8>().map_err(|_| vox_error("bad _r"))?;
+    let i0 = r & 0b11;
+    let i1 = (r >> 2) & 0b11;
+    if i0 >= 3 || i1 >= 3 || i0 == i1 {
+        return Err(vox_error("bad _r"));
+    }
+    Ok(r)
+}
+
+// ----------------------------------------------------------------------------
+// Binary encoding
+// ----------------------------------------------------------------------------
+
+fn vox_error(message: &str) -> Error {
+    Error::VoxFormat(message.to_string())
+}
+
+fn write_i32(out: &mut Vec<u8>, v: i32) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn write_string(out: &mut Vec<u8>, s: &str) {
+    write_i32(out, s.len() as i32);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn write_dict(out: &mut Vec<u8>, entries: &[(&str, String)]) {
+    write_i32(out, entries.len() as i32);
+    for (key, value) in entries {
+        write_string(out, key);
+        write_string(out, value);
+    }
+}
+
+fn write_chunk(out: &mut Vec<u8>, id: &[u8; 4], content: &[u8], children: &[u8]) {
+    out.extend_from_slice(id);
+    write_i32(out, content.len() as i32);
+    write_i32(out, children.len() as i32);
+    out.extend_from_slice(content);
+    out.extend_from_slice(children);
+}
+
+struct VoxChunk<'a> {
+    content: &'a [u8],
+    children: &'a [u8],
+}
+
+struct VoxReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> VoxReader<'a> {
+    fn is_done(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len().saturating_sub(self.pos)
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        if self.pos + n > self.bytes.len() {
+            return Err(vox_error("unexpected end of data"));
+        }
+        let slice = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn i32(&mut self) -> Result<i32, Error> {
+        let b = self.take(4)?;
+        Ok(i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn len(&mut self) -> Result<usize, Error> {
+        let n = self.i32()?;
+        if n < 0 {
+            return Err(vox_error("negative length"));
+        }
+        Ok(n as usize)
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        let n = self.len()?;
+        Ok(String::from_utf8_lossy(self.take(n)?).to_string())
+    }
+
+    fn dict(&mut self) -> Result<HashMap<String, String>, Error> {
+        let n = self.len()?;
+        let mut dict = HashMap::new();
+        for _ in 0..n {
+            let key = self.string()?;
+            let value = self.string()?;
+            dict.insert(key, value);
+        }
+        Ok(dict)
+    }
+
+    fn chunk(&mut self) -> Result<([u8; 4], VoxChunk<'a>), Error> {
+        let id = self.take(4)?;
+        let id = [id[0], id[1], id[2], id[3]];
+        let content_len = self.len()?;
+        let children_len = self.len()?;
+        let content = self.take(content_len)?;
+        let children = self.take(children_len)?;
+        Ok((id, VoxChunk { content, children }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_vox_round_trip() {
+        let mut model = VoxelSet::new();
+        model.register_block(Block::color("red", 255, 0, 0));
+        model.register_block(Block::color("blue", 0, 0, 255));
+        model.fill_box((-3, -2, 0), (4, 1, 2), "red");
+        model.set_voxel((0, 0, 3), "blue");
+
+        let bytes = model.to_vox_bytes();
+        let loaded = VoxelSet::from_vox_bytes(&bytes).unwrap();
+
+        assert_eq!(
+            loaded.voxel_iter(false).len(),
+            model.voxel_iter(false).len()
+        );
+        for (p, block) in model.voxel_iter(false) {
+            assert_eq!(loaded.get_voxel(p).shader, block.shader);
+        }
+    }
+
+    #[test]
+    fn test_vox_splits_large_models() {
+        let mut model = VoxelSet::new();
+        model.register_block(Block::color("red", 255, 0, 0));
+        model.set_voxel((-10, 0, 0), "red");
+        model.set_voxel((300, 5, 0), "red");
+        model.set_voxel((301, 5, 600), "red");
+
+        let bytes = model.to_vox_bytes();
+        let loaded = VoxelSet::from_vox_bytes(&bytes).unwrap();
+        for (p, _) in model.voxel_iter(false) {
+            assert_eq!(loaded.get_voxel(p).id, "vox_1");
+        }
+        assert_eq!(loaded.voxel_iter(false).len(), 3);
+    }
+
+    #[test]
+    fn test_vox_rejects_bad_header() {
+        assert!(VoxelSet::from_vox_bytes(b"NOPE").is_err());
+        assert!(VoxelSet::from_vox_bytes(b"VOX \x96\0\0\0MAIN").is_err());
+    }
+
+    #[test]
+    fn test_vox_rejects_oversized_xyzi_count() {
+        let mut model = VoxelSet::new();
+        model.register_block(Block::color("red", 255, 0, 0));
+        model.set_voxel((0, 0, 0), "red");
+
+        let mut bytes = model.to_vox_bytes();
+        let at = bytes.windows(4).position(|w| w == b"XYZI").unwrap() + 12;
+        bytes[at..at + 4].copy_from_slice(&i32::MAX.to_le_bytes());
+        assert!(VoxelSet::from_vox_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_vox_rotation_byte() {
+        // Row 0 picks y, row 1 picks x, so row 2 is z; row 0 is negated
+        let r = parse_vox_rotation("17").unwrap();
+        let transform = VoxTransform::from_rotation(r, IVec3::ZERO);
+        assert_eq!(transform.apply(IVec3::new(1, 2, 3)), IVec3::new(-2, 1, 3));
+
+        for bad in ["10", "15", "0", "5", "256", "x"] {
+            assert!(parse_vox_rotation(bad).is_err(), "{}", bad);
+        }
+    }
+}
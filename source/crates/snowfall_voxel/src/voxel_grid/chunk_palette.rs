@@ -17,7 +17,8 @@ impl ChunkPalette {
                         return i as u8;
                     }
                 }
-                for i in 0..16 {
+                // Local index 0 is always the empty block
+                for i in 1..16 {
                     if entries[i] == 0 {
                         entries[i] = block_index;
                         return i as u8;
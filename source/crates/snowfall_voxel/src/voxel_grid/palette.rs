@@ -7,9 +7,10 @@ pub struct Palette {
 
 impl Palette {
     pub fn new() -> Self {
+        let empty = Block::empty();
         Self {
-            blocks: vec![Block::empty()],
-            block_index: HashMap::new(),
+            block_index: HashMap::from([(empty.id.clone(), 0)]),
+            blocks: vec![empty],
         }
     }
 
@@ -19,6 +20,28 @@ impl Palette {
         self.block_index.insert(block.id.clone(), index);
     }
 
+    /// Returns the index of the block, registering it if needed.  If the id
+    /// is already used by a different block definition, the block is added
+    /// under its variant id instead.
+    pub fn ensure(&mut self, block: &Block) -> usize {
+        if block.is_empty() && block.id == "empty" {
+            return 0;
+        }
+        let block = match self.block_by_id(&block.id) {
+            Some(existing) if existing.is_equivalent(block) => return self.block_index[&block.id],
+            Some(_) => Block {
+                id: block.variant_id(),
+                ..block.clone()
+            },
+            None => block.clone(),
+        };
+        if let Some(index) = self.index_by_id(&block.id) {
+            return index;
+        }
+        self.register(block);
+        self.blocks.len() - 1
+    }
+
     pub fn index_by_id(&self, id: &str) -> Option<usize> {
         self.block_index.get(id).copied()
     }
@@ -14,6 +14,21 @@ pub trait VoxelGridPager {
 /// Interface for providing the contents of a new chunk.
 pub trait VoxelGridGenerator {
     fn generate(&self, world_position: IVec3) -> &str;
+
+    /// Fills `column` with the blocks from `base` upwards.  Generators that
+    /// do per-column work, such as computing a surface height, can override
+    /// this to do it once per column rather than once per voxel.
+    fn generate_column<'a>(&'a self, base: IVec3, column: &mut [&'a str]) {
+        for (dz, block) in column.iter_mut().enumerate() {
+            *block = self.generate(base + IVec3::new(0, 0, dz as i32));
+        }
+    }
+
+    /// Blocks `generate` may return, which are registered in the grid's
+    /// palette when the generator is attached.
+    fn blocks(&self) -> Vec<Block> {
+        Vec::new()
+    }
 }
 
 /// VoxelGrid is a 3D grid of voxels designed for handling unbounded, sparse
@@ -48,6 +63,19 @@ impl VoxelGrid {
         }
     }
 
+    /// Generates chunks that are not in memory (or paged out) with the
+    /// generator.
+    pub fn with_generator<G>(mut self, generator: G) -> Self
+    where
+        G: VoxelGridGenerator + 'static,
+    {
+        for block in generator.blocks() {
+            self.palette.ensure(&block);
+        }
+        self.generator = Some(Box::new(generator));
+        self
+    }
+
     // ------------------------------------------------------------------------
     // Properties & Utilities
     // ------------------------------------------------------------------------
@@ -78,6 +106,14 @@ impl VoxelGrid {
         self.palette.register(block);
     }
 
+    pub fn ensure_block(&mut self, block: &Block) -> usize {
+        self.palette.ensure(block)
+    }
+
+    pub fn block_index(&self, id: &str) -> Option<usize> {
+        self.palette.index_by_id(id)
+    }
+
     // ------------------------------------------------------------------------
     // Voxels
     // ------------------------------------------------------------------------
@@ -85,7 +121,10 @@ impl VoxelGrid {
     /// Note: this method intentionally takes a mutable reference to self
     /// as it can create new chunks if a chunk that's not yet in memory is
     /// queried.
-    pub fn is_empty(&mut self, p: IVec3) -> bool {
+    pub fn is_empty<S>(&mut self, p: S) -> bool
+    where
+        S: Into<IVec3>,
+    {
         let (chunk_pos, inner_pos) = chunk_coords(p.into());
         let chunk = self.ensure_chunk(chunk_pos);
         chunk.is_empty(inner_pos)
@@ -121,6 +160,15 @@ impl VoxelGrid {
         chunk.set(inner_pos, block_index);
     }
 
+    pub fn set_index<S>(&mut self, p: S, block_index: usize)
+    where
+        S: Into<IVec3>,
+    {
+        let (chunk_pos, inner_pos) = chunk_coords(p.into());
+        let chunk = self.ensure_chunk(chunk_pos);
+        chunk.set(inner_pos, block_index);
+    }
+
     // ------------------------------------------------------------------------
     // Chunks
     // ------------------------------------------------------------------------
@@ -153,13 +201,13 @@ impl VoxelGrid {
         );
 
         let mut chunk = ChunkFull::new();
+        let mut column = [""; CHUNK_DIM_Z];
         for dy in 0..CHUNK_DIM_Y as u8 {
             for dx in 0..CHUNK_DIM_X as u8 {
-                for dz in 0..CHUNK_DIM_Z as u8 {
-                    let p = base + IVec3::new(dx as i32, dy as i32, dz as i32);
-                    let block_name = generator.generate(p);
+                generator.generate_column(base + IVec3::new(dx as i32, dy as i32, 0), &mut column);
+                for (dz, block_name) in column.iter().enumerate() {
                     let block_index = palette.index_by_id(block_name).unwrap();
-                    chunk.set((dx, dy, dz), block_index);
+                    chunk.set((dx, dy, dz as u8), block_index);
                 }
             }
         }
@@ -167,7 +215,7 @@ impl VoxelGrid {
         // This optimization matters. Don't generate full chunks that contain
         // exclusively empty voxels. At the same time, we don't want the
         // generator to have to figure out if it is going to generate a fully
-        // empty chunk since they work a voxel at a time.
+        // empty chunk since they work a voxel (or column) at a time.
         if chunk.is_chunk_empty() {
             Chunk::Empty
         } else {
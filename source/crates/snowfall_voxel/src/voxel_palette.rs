@@ -1,6 +1,6 @@
 use crate::internal::*;
 
-#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct PaletteIndex(u16);
 
 impl PaletteIndex {
@@ -42,6 +42,20 @@ impl PaletteIndexAlias for String {
     }
 }
 
+/// Maps the indices of one palette onto the indices of another, as returned
+/// by VoxelPalette::merge.
+pub struct PaletteRemap(Vec<PaletteIndex>);
+
+impl PaletteRemap {
+    /// Indices outside the source palette map to the empty block.
+    pub fn get(&self, index: PaletteIndex) -> PaletteIndex {
+        self.0
+            .get(index.0 as usize)
+            .copied()
+            .unwrap_or(PaletteIndex::zero())
+    }
+}
+
 /// By definition, index 0 is **always** an empty block.
 ///
 #[derive(Serialize, Deserialize, Clone)]
@@ -80,19 +94,57 @@ impl VoxelPalette {
         PaletteIndex(i)
     }
 
+    /// Merges the blocks of another palette into this one, returning a table
+    /// that maps each index in `other` to the corresponding index in `self`.
+    ///
+    /// Identical blocks are shared.  If a block id is already in use by a
+    /// block with a different definition, the incoming block is added under
+    /// its variant id rather than replacing the existing definition.
+    pub fn merge(&mut self, other: &VoxelPalette) -> PaletteRemap {
+        PaletteRemap(other.blocks.iter().map(|b| self.merge_block(b)).collect())
+    }
+
+    /// Returns the index of an identical block in this palette, adding the
+    /// block first if needed.  See merge() for how id conflicts are handled.
+    pub fn merge_block(&mut self, block: &Block) -> PaletteIndex {
+        if block.is_empty() && block.id == "empty" {
+            return PaletteIndex::zero();
+        }
+        match self.blocks.iter().position(|b| b.id == block.id) {
+            Some(i) if self.blocks[i].is_equivalent(block) => PaletteIndex(i as u16),
+            Some(_) => {
+                let mut block = block.clone();
+                block.id = block.variant_id();
+                match self.blocks.iter().position(|b| b.id == block.id) {
+                    Some(i) => PaletteIndex(i as u16),
+                    None => self.register(block),
+                }
+            }
+            None => self.register(block.clone()),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (PaletteIndex, &Block)> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (PaletteIndex(i as u16), b))
+    }
+
     pub fn get(&self, index: PaletteIndex) -> Option<&Block> {
         self.blocks.get(index.0 as usize)
     }
 
     pub fn index_for_id(&self, id: &str) -> PaletteIndex {
-        match self
-            .blocks
+        self.find_id(id).unwrap_or(PaletteIndex::zero())
+    }
+
+    /// Like index_for_id() but returns None rather than the empty block when
+    /// the id is not in the palette.
+    pub fn find_id(&self, id: &str) -> Option<PaletteIndex> {
+        self.blocks
             .iter()
             .position(|b| b.id == id)
             .map(|i| PaletteIndex(i as u16))
-        {
-            Some(index) => index,
-            None => PaletteIndex::zero(),
-        }
     }
 }
@@ -44,10 +44,8 @@ impl Layer {
 /// Provides the parameters needed to generate a particular
 /// model dynamically (or from disk).
 ///
-/// TODO:
-///
-/// - Rotation (in 90 degree increments) and mirror
-///   - Worth constraining rather than an arbitrary quaternion?
+/// The orientation is constrained to 90 degree rotations and mirroring
+/// rather than an arbitrary quaternion so the model remains voxel-aligned.
 #[derive(Serialize, Deserialize)]
 pub struct VoxelModelRef {
     pub model_id: String,
@@ -56,6 +54,8 @@ pub struct VoxelModelRef {
     pub params: serde_json::Value,
 
     pub position: IVec3,
+    #[serde(default)]
+    pub orientation: Orientation,
 }
 
 pub const VOXEL_SCENE_FILE_IDENTIFIER: &str = "SNOWFALL_VOXEL_SCENE";
@@ -76,4 +76,36 @@ impl VoxelSceneFile {
             scene,
         }
     }
+
+    /// Parses a scene file, migrating older versions to the current one.
+    pub fn from_yaml_str(s: &str) -> Result<Self, Error> {
+        #[derive(Deserialize)]
+        struct RawFile {
+            identifier: String,
+            version: String,
+            scene: serde_yaml::Value,
+        }
+
+        let corrupt = |e: serde_yaml::Error| Error::CorruptData(e.to_string());
+        let raw: RawFile = serde_yaml::from_str(s).map_err(corrupt)?;
+        if raw.identifier != VOXEL_SCENE_FILE_IDENTIFIER {
+            return Err(Error::FileHeader(raw.identifier));
+        }
+        let scene = voxel_scene_migrations().migrate(&raw.version, raw.scene)?;
+        let scene: VoxelScene = serde_yaml::from_value(scene).map_err(corrupt)?;
+        Ok(Self::new(scene))
+    }
+
+    pub fn to_yaml_string(&self) -> Result<String, Error> {
+        serde_yaml::to_string(self).map_err(|e| Error::CorruptData(e.to_string()))
+    }
+
+    pub fn read_file(path: &str) -> Result<Self, Error> {
+        Self::from_yaml_str(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn write_file(&self, path: &str) -> Result<(), Error> {
+        std::fs::write(path, self.to_yaml_string()?)?;
+        Ok(())
+    }
 }
@@ -2,7 +2,7 @@ use crate::internal::*;
 use bevy_math::{Vec2, Vec3};
 use snowfall_core::prelude::*;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub enum VoxelSetAttribute {
     Unlit,
     RotateZ(f32),
@@ -10,6 +10,14 @@ pub enum VoxelSetAttribute {
     BillboardZ,
 }
 
+/// Information generators record about their output for later steps, such
+/// as where paths should connect to a building.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct VoxelSetMetadata {
+    /// Named positions in the set's coordinates, e.g. `"entrance"`
+    pub anchors: std::collections::BTreeMap<String, IVec3>,
+}
+
 /// VoxelSet is simplified voxel representation designed for smaller models
 /// that are bounded and can have all chunks loaded into memory at once.
 ///
@@ -17,11 +25,12 @@ pub enum VoxelSetAttribute {
 /// models or small scenes rather than unbounded terrain data.  It priorities
 /// ease-of-use for small models over performance and scalability.
 ///
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct VoxelSet {
     generation: u64, // Generation number used to track changes
     pub palette: VoxelPalette,
     pub attributes: Vec<VoxelSetAttribute>,
+    pub metadata: VoxelSetMetadata,
 
     // Storing the data by z-column is *much* faster in any context where
     // "height at x,y" is a common operation.
@@ -34,10 +43,39 @@ impl VoxelSet {
             generation: 0,
             palette: VoxelPalette::new(),
             attributes: Vec::new(),
+            metadata: VoxelSetMetadata::default(),
+            data: HashMap::new(),
+        }
+    }
+
+    /// Creates an empty voxel set with the same palette, attributes, and
+    /// metadata as this one.  Useful for building transformed copies of a set
+    /// where the palette indices can be carried over as-is.
+    pub fn empty_copy(&self) -> Self {
+        VoxelSet {
+            generation: 0,
+            palette: self.palette.clone(),
+            attributes: self.attributes.clone(),
+            metadata: self.metadata.clone(),
             data: HashMap::new(),
         }
     }
 
+    // ------------------------------------------------------------------------
+    // Metadata
+    // ------------------------------------------------------------------------
+
+    pub fn set_anchor<S>(&mut self, name: S, p: IVec3)
+    where
+        S: Into<String>,
+    {
+        self.metadata.anchors.insert(name.into(), p);
+    }
+
+    pub fn anchor(&self, name: &str) -> Option<IVec3> {
+        self.metadata.anchors.get(name).copied()
+    }
+
     // ------------------------------------------------------------------------
     // Block palette
     // ------------------------------------------------------------------------
@@ -164,6 +202,23 @@ impl VoxelSet {
         column.insert(vc.z, index);
     }
 
+    /// Sets every voxel in the inclusive box from min to max.
+    pub fn fill_box<P, I>(&mut self, min: P, max: P, id: I)
+    where
+        P: Into<IVec3>,
+        I: PaletteIndexAlias,
+    {
+        let (min, max) = (min.into(), max.into());
+        let index = id.as_index(&self.palette);
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    self.set((x, y, z), index);
+                }
+            }
+        }
+    }
+
     pub fn set_voxel<S, T>(&mut self, vc: S, id: T)
     where
         S: Into<IVec3>,
@@ -197,6 +252,27 @@ impl VoxelSet {
         column.insert(vc.z, new_index);
     }
 
+    /// Returns the position and palette index of every non-empty voxel.
+    pub fn index_iter(&self) -> impl Iterator<Item = (IVec3, PaletteIndex)> + '_ {
+        self.data.iter().flat_map(|(&(x, y), column)| {
+            column
+                .iter()
+                .filter(|(_, id)| !id.is_zero())
+                .map(move |(&z, &id)| (IVec3::new(x, y, z), id))
+        })
+    }
+
+    /// Returns the positions of voxels that have been explicitly set to the
+    /// empty block, as opposed to never having been set at all.
+    pub fn explicit_empty_iter(&self) -> impl Iterator<Item = IVec3> + '_ {
+        self.data.iter().flat_map(|(&(x, y), column)| {
+            column
+                .iter()
+                .filter(|(_, id)| id.is_zero())
+                .map(move |(&z, _)| IVec3::new(x, y, z))
+        })
+    }
+
     pub fn voxel_iter(&self, include_empty: bool) -> Vec<(IVec3, &Block)> {
         // Collect all the voxels into a vec
         let mut voxels = Vec::new();
@@ -217,45 +293,72 @@ impl VoxelSet {
     // Serialization
     // ------------------------------------------------------------------------
 
-    pub fn serialize_to_file(&self, path: &str) {
+    pub fn serialize_to_file(&self, path: &str) -> Result<(), Error> {
+        std::fs::write(path, self.serialize_to_bytes()?)?;
+        Ok(())
+    }
+
+    pub fn deserialize_from_file(path: &str) -> Result<Self, Error> {
+        let bytes = std::fs::read(path)?;
+        Self::deserialize_from_bytes(&bytes)
+    }
+
+    pub fn serialize_to_file_with(
+        &self,
+        path: &str,
+        compression: &Compression,
+    ) -> Result<(), Error> {
+        std::fs::write(path, self.serialize_to_bytes_with(compression)?)?;
+        Ok(())
+    }
+
+    pub fn serialize_to_bytes(&self) -> Result<Vec<u8>, Error> {
+        self.serialize_to_bytes_with(&Compression::default())
+    }
+
+    pub fn serialize_to_bytes_with(&self, compression: &Compression) -> Result<Vec<u8>, Error> {
         let file = VoxelSetFile {
             identifier: VOXEL_SET_FILE_IDENTIFIER,
-            version: [0, 0, 1, 0],
-            compressed_voxel_set: serialize_and_compress(self),
+            version: VOXEL_SET_FILE_VERSION,
+            codec: compression.codec.id(),
+            compressed_voxel_set: serialize_and_compress_with(self, compression)?,
         };
-        let Ok(bytes) = serialize_to_bytes(&file) else {
-            panic!("Failed to serialize voxel set");
-        };
-        std::fs::write(path, &bytes).expect("Failed to write file");
+        serialize_to_bytes(&file)
     }
 
-    pub fn deserialize_from_file(path: &str) -> Result<Self, Error> {
-        // Read the file at path as a byte array
-        let bytes = std::fs::read(path).unwrap();
-        let voxel_file = deserialize_from_bytes::<VoxelSetFile>(&bytes)?;
-
-        // At this stage in development, we're not worried about backwards compatibility
-        // so panic!() on anything we don't recognize.
-        if voxel_file.identifier != VOXEL_SET_FILE_IDENTIFIER {
+    pub fn deserialize_from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        // Every version starts with the identifier and version, so read those
+        // first to know how to interpret the rest.
+        let header = deserialize_from_bytes::<VoxelSetFileHeader>(bytes)
+            .map_err(|e| Error::CorruptData(e.to_string()))?;
+        if header.identifier != VOXEL_SET_FILE_IDENTIFIER {
             return Err(Error::FileHeader(
-                String::from_utf8_lossy(&voxel_file.identifier).to_string(),
+                String::from_utf8_lossy(&header.identifier).to_string(),
             ));
         }
-        if voxel_file.version != [0, 0, 1, 0] {
-            return Err(Error::FileVersion(format!("{:?}", voxel_file.version)));
-        }
-        let voxel_set: Self = decompress_and_deserialize(&voxel_file.compressed_voxel_set);
-        Ok(voxel_set)
+
+        let bytes = voxel_set_migrations().migrate(&header.version, bytes.to_vec())?;
+        let voxel_file = deserialize_from_bytes::<VoxelSetFile>(&bytes)
+            .map_err(|e| Error::CorruptData(e.to_string()))?;
+        let codec = Codec::from_id(voxel_file.codec)?;
+        decompress_and_deserialize_with(&voxel_file.compressed_voxel_set, codec)
     }
 }
 
-const VOXEL_SET_FILE_IDENTIFIER: [u8; 8] = *b"SNVSET\0\0";
+pub(crate) const VOXEL_SET_FILE_IDENTIFIER: [u8; 8] = *b"SNVSET\0\0";
 
 #[derive(Serialize, Deserialize)]
-struct VoxelSetFile {
+struct VoxelSetFileHeader {
     identifier: [u8; 8],
     version: [u8; 4],
-    compressed_voxel_set: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct VoxelSetFile {
+    pub identifier: [u8; 8],
+    pub version: [u8; 4],
+    pub codec: u8,
+    pub compressed_voxel_set: Vec<u8>,
 }
 
 pub struct VoxelMesh {
@@ -408,4 +511,37 @@ mod test {
         assert_eq!(model.get_voxel((0, 0, 1)).id, "test");
         assert_eq!(model.get_voxel((0, 0, 2)).id, "empty");
     }
+
+    #[test]
+    fn test_voxel_set_corrupt_bytes() {
+        let mut model = VoxelSet::new();
+        model.register_block(Block::color("test", 255, 0, 0));
+        model.fill_box((0, 0, 0), (3, 3, 3), "test");
+
+        let bytes = model.serialize_to_bytes().unwrap();
+        let copy = VoxelSet::deserialize_from_bytes(&bytes).unwrap();
+        assert_eq!(copy.voxel_iter(false).len(), 64);
+
+        let truncated = &bytes[..bytes.len() / 2];
+        assert!(matches!(
+            VoxelSet::deserialize_from_bytes(truncated),
+            Err(Error::CorruptData(_))
+        ));
+
+        // Damage the compressed payload but keep the container intact
+        let mut damaged = bytes.clone();
+        let n = damaged.len();
+        for b in damaged[n - 16..].iter_mut() {
+            *b = 0xff;
+        }
+        assert!(matches!(
+            VoxelSet::deserialize_from_bytes(&damaged),
+            Err(Error::Decompression(_)) | Err(Error::CorruptData(_))
+        ));
+
+        assert!(matches!(
+            VoxelSet::deserialize_from_file("/nonexistent/model.vset"),
+            Err(Error::Io(_))
+        ));
+    }
 }
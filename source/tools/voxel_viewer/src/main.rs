@@ -13,21 +13,38 @@ use clap::Parser;
 struct CLIArguments {
     generator: String,
     seed: u64,
+
+    /// Always run the generators rather than using cached output
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Delete all cached generator output before generating
+    #[arg(long)]
+    clear_cache: bool,
 }
 
 #[derive(Resource)]
 struct AppState {
     generator: String,
     seed: u64,
+    cache: Option<GenCache>,
+    registry: std::sync::Arc<GeneratorRegistry>,
     view_radius: f32,
     look_at: Vec3,
 }
 
 impl AppState {
-    fn new(generator: String, seed: u64) -> Self {
+    fn new(
+        generator: String,
+        seed: u64,
+        cache: Option<GenCache>,
+        registry: GeneratorRegistry,
+    ) -> Self {
         Self {
             generator,
             seed,
+            cache,
+            registry: std::sync::Arc::new(registry),
             view_radius: 32.0,
             look_at: Vec3::new(0.0, 0.0, 1.0),
         }
@@ -37,13 +54,33 @@ impl AppState {
 fn main() {
     let args = CLIArguments::parse();
 
+    let cache = GenCache::new("./cache/models");
+    if args.clear_cache {
+        cache.clear().unwrap();
+    }
+    let cache = (!args.no_cache).then_some(cache);
+
+    // Recipes in content/recipes are available alongside the built-in
+    // generators.  A bad recipe is reported rather than stopping the viewer.
+    let registry = if std::path::Path::new("content/recipes").exists() {
+        match GeneratorRegistry::with_builtins().register_recipe_dir("content/recipes") {
+            Ok(registry) => registry,
+            Err(e) => {
+                eprintln!("Failed to load recipes: {}", e);
+                GeneratorRegistry::with_builtins()
+            }
+        }
+    } else {
+        GeneratorRegistry::with_builtins()
+    };
+
     let mut app = App::new();
     app //
         .add_plugins(DefaultPlugins.set(bevy::log::LogPlugin {
             level: bevy::log::Level::WARN,
             ..default()
         }))
-        .insert_resource(AppState::new(args.generator, args.seed))
+        .insert_resource(AppState::new(args.generator, args.seed, cache, registry))
         .add_systems(
             Startup,
             (
@@ -116,7 +153,9 @@ fn startup_scene(
         state.generator.as_str(),
         state.seed,
         IVec3::ZERO,
+        Orientation::default(),
         serde_json::Value::Null,
+        &base_context(&state),
         &mut scene,
     );
 
@@ -131,6 +170,7 @@ fn startup_scene(
             seed: 0,
             params: serde_json::Value::Null,
             position: IVec3::ZERO,
+            orientation: Orientation::default(),
             imp: ObjectImp::VoxelSet(Box::new(scene.terrain)),
         },
         &mut scene_bounds,
@@ -167,13 +207,14 @@ fn spawn_model(
         ObjectImp::Empty => {}
         ObjectImp::Stub => {}
         ObjectImp::Actor(_) => {}
-        ObjectImp::VoxelSet(model) => {
+        ObjectImp::VoxelSet(_) => {
+            let model = obj.oriented_voxel_set().unwrap();
             let mut bounds = model.bounds();
             bounds.translate(obj.position);
             scene_bounds.merge(&bounds);
 
             VoxelMeshComponent::spawn_from_model(
-                model,
+                &model,
                 commands,
                 meshes,
                 materials,
@@ -192,14 +233,26 @@ fn spawn_model(
     }
 }
 
+/// Context that the root and every referenced model are forked from.
+fn base_context(state: &AppState) -> GenContext {
+    let ctx =
+        GenContext::new(state.generator.clone(), state.seed).with_registry(state.registry.clone());
+    match &state.cache {
+        Some(cache) => ctx.with_cache(cache.clone()),
+        None => ctx,
+    }
+}
+
 fn generate(
     generator: &str,
     seed: u64,
     center: IVec3,
+    orientation: Orientation,
     params: serde_json::Value,
+    base: &GenContext,
     scene: &mut Scene2,
 ) -> Object {
-    let mut ctx = GenContext::new(generator, seed);
+    let mut ctx = base.fork(generator, seed);
     ctx.center = center;
     ctx.params = params.clone();
     let filename = format!("content/{}-{}.yaml", generator, seed);
@@ -207,12 +260,17 @@ fn generate(
     // Check if filename exists
     let model = if std::path::Path::new(&filename).exists() {
         println!("Loading model from file: {}", &filename);
-        let contents = std::fs::read_to_string(&filename).unwrap();
-        let file: VoxelSceneFile = serde_yaml::from_str(&contents).unwrap();
+        let file = VoxelSceneFile::read_file(&filename).unwrap();
         VoxelModel::VoxelScene(Box::new(file.scene))
     } else {
         println!("Generating model: {}", &filename);
-        generate_model(&ctx, scene)
+        match try_generate_model(&ctx, scene) {
+            Ok(model) => model,
+            Err(e) => {
+                eprintln!("Failed to generate {} {}: {}", generator, seed, e);
+                VoxelModel::Empty
+            }
+        }
     };
 
     Object {
@@ -220,6 +278,7 @@ fn generate(
         seed,
         params: params.clone(),
         position: center,
+        orientation,
         imp: match model {
             VoxelModel::Empty => {
                 println!("Empty model: {} {}", generator, seed);
@@ -236,7 +295,9 @@ fn generate(
                             object.model_id.clone().as_str(),
                             object.seed,
                             object.position,
+                            object.orientation,
                             object.params.clone(),
+                            base,
                             scene,
                         );
                         group.objects.push(obj);